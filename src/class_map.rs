@@ -0,0 +1,564 @@
+/* class renaming, merging, dropping and reordering from a yaml mapping file */
+use crate::fileutils::{
+    export_file, find_files, read_data_yaml, relative_path, write_dataset_info, DiscoveryOptions,
+    ExportMode, IMAGE_EXTENSIONS,
+};
+use crate::label::LabelmeAnnotation;
+use anyhow::{bail, Error, Result};
+use globset::{Glob, GlobMatcher};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// what happens to classes no rule matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmappedPolicy {
+    #[default]
+    Keep,
+    Drop,
+}
+
+/// a single rule of the mapping file, either `from` or `regex`
+/// and either `to` or `drop: true`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassRule {
+    /// glob on the class name, `10C` or `*C`
+    pub from: Option<String>,
+    /// regex on the whole class name, `to` can use its groups (`$1`)
+    pub regex: Option<String>,
+    pub to: Option<String>,
+    #[serde(default)]
+    pub drop: bool,
+}
+
+/// ```yaml
+/// rules:
+///   - from: "10C"
+///     to: ten_clubs
+///   - from: "*C"
+///     to: club
+///   - regex: "(\\d+)H"
+///     to: "heart_$1"
+///   - from: joker
+///     drop: true
+/// order: [club, ten_clubs]
+/// # or take the order of an existing model, relative to this file
+/// order_from: data.yaml
+/// unmapped: keep
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassMapFile {
+    #[serde(default)]
+    pub rules: Vec<ClassRule>,
+    #[serde(default)]
+    pub order: Vec<String>,
+    pub order_from: Option<String>,
+    #[serde(default)]
+    pub unmapped: UnmappedPolicy,
+}
+
+#[derive(Debug, Clone)]
+enum ClassMatcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+/// a compiled mapping file, the first matching rule wins
+#[derive(Debug, Clone)]
+pub struct ClassMap {
+    rules: Vec<(ClassMatcher, Option<String>)>,
+    /// classes that come first, in this order
+    pub order: Vec<String>,
+    pub unmapped: UnmappedPolicy,
+}
+
+impl ClassMap {
+    /// reads a mapping file, `order_from` is relative to it
+    pub fn from_file(path: &Path) -> Result<ClassMap, Error> {
+        let yaml_str = match fs::read_to_string(path) {
+            Ok(yaml_str) => yaml_str,
+            Err(e) => bail!("[error]::class_map: cannot read {:?}: {}", path, e),
+        };
+        ClassMap::from_yaml(&yaml_str, path.parent().unwrap_or(Path::new("")))
+    }
+
+    pub fn from_yaml(yaml_str: &str, base: &Path) -> Result<ClassMap, Error> {
+        let map_file: ClassMapFile = serde_yaml::from_str(yaml_str)?;
+        let mut rules = vec![];
+        for rule in map_file.rules.iter() {
+            let matcher = match (&rule.from, &rule.regex) {
+                (Some(from), None) => ClassMatcher::Glob(Glob::new(from)?.compile_matcher()),
+                (None, Some(regex)) => {
+                    ClassMatcher::Regex(Regex::new(&format!("^(?:{})$", regex))?)
+                }
+                _ => bail!(
+                    "[error]::class_map: every rule needs either `from` or `regex`: {:?}",
+                    rule
+                ),
+            };
+            let action = match (&rule.to, rule.drop) {
+                (Some(to), false) => Some(to.to_owned()),
+                (None, true) => None,
+                _ => bail!(
+                    "[error]::class_map: every rule needs either `to` or `drop: true`: {:?}",
+                    rule
+                ),
+            };
+            rules.push((matcher, action));
+        }
+        let order = match (&map_file.order_from, map_file.order.is_empty()) {
+            (Some(_), false) => {
+                bail!("[error]::class_map: use either `order` or `order_from`, not both")
+            }
+            (Some(data_yaml), true) => read_data_yaml(&base.join(data_yaml))?.names,
+            (None, _) => map_file.order.to_owned(),
+        };
+        Ok(ClassMap {
+            rules,
+            order,
+            unmapped: map_file.unmapped,
+        })
+    }
+
+    /// the new name of a class, `None` if it's dropped
+    pub fn map_label(&self, label: &str) -> Option<String> {
+        for (matcher, action) in self.rules.iter() {
+            let mapped = match matcher {
+                ClassMatcher::Glob(glob) if glob.is_match(label) => action.to_owned(),
+                ClassMatcher::Regex(regex) if regex.is_match(label) => action
+                    .as_ref()
+                    .map(|to| regex.replace(label, to.as_str()).to_string()),
+                _ => continue,
+            };
+            return mapped;
+        }
+        match self.unmapped {
+            UnmappedPolicy::Keep => Some(label.to_owned()),
+            UnmappedPolicy::Drop => None,
+        }
+    }
+
+    /// `order` first, then the other mapped classes sorted
+    pub fn class_order<'a>(&self, labels: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        let mut classes = self.order.to_owned();
+        let extra: BTreeSet<String> = labels
+            .into_iter()
+            .filter_map(|label| self.map_label(label))
+            .filter(|label| !self.order.contains(label))
+            .collect();
+        classes.extend(extra);
+        classes
+    }
+
+    /// maps the shapes of an annotation in place,
+    /// returns the amount of renamed and dropped shapes
+    pub fn apply_to_labelme(&self, labelme: &mut LabelmeAnnotation) -> (usize, usize) {
+        let (mut renamed, mut dropped) = (0, 0);
+        let shapes = std::mem::take(&mut labelme.shapes);
+        for mut shape in shapes.into_iter() {
+            match self.map_label(&shape.label) {
+                Some(label) => {
+                    if label != shape.label {
+                        renamed += 1;
+                        shape.label = label;
+                    }
+                    labelme.shapes.push(shape);
+                }
+                None => dropped += 1,
+            }
+        }
+        (renamed, dropped)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RemapOptions {
+    /// write the remapped dataset here instead of in place
+    pub output: Option<PathBuf>,
+    /// only report what would change
+    pub dry_run: bool,
+    /// class names of a yolo dataset
+    pub data_yaml: Option<PathBuf>,
+    pub discovery: DiscoveryOptions,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RemapReport {
+    pub files_checked: usize,
+    pub files_changed: usize,
+    /// shapes with a new class name (or a new yolo class id)
+    pub shapes_renamed: usize,
+    pub shapes_dropped: usize,
+    /// every class seen and what it became, `null` if dropped
+    pub mapping: BTreeMap<String, Option<String>>,
+    /// class list after remapping, the yolo index order
+    pub classes: Vec<String>,
+}
+
+/// remaps every labelme json in a folder, in place or into `options.output`
+/// (jsons and their images, changed or not)
+pub fn remap_labelme_folder(
+    folder: &Path,
+    class_map: &ClassMap,
+    options: &RemapOptions,
+) -> Result<RemapReport, Error> {
+    let mut discovery = options.discovery.to_owned();
+    discovery.skip_folders.extend(options.output.to_owned());
+    let all_json = find_files(folder, &["json"], &discovery)?;
+    let results = all_json
+        .par_iter()
+        .map(|json| -> Result<(Vec<String>, usize, usize), Error> {
+            let mut labelme: LabelmeAnnotation = match fs::read_to_string(json)
+                .map_err(Error::from)
+                .and_then(|json_str| Ok(serde_json::from_str(&json_str)?))
+            {
+                Ok(labelme) => labelme,
+                Err(e) => bail!(
+                    "[error]::class_map: {:?} is not a labelme json: {}",
+                    json,
+                    e
+                ),
+            };
+            let labels = labelme.shapes.iter().map(|s| s.label.to_owned()).collect();
+            let (renamed, dropped) = class_map.apply_to_labelme(&mut labelme);
+            if options.dry_run {
+                return Ok((labels, renamed, dropped));
+            }
+            match &options.output {
+                Some(output) => {
+                    let image = json
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                        .join(&labelme.imagePath);
+                    let json_dest = output.join(relative_path(folder, json));
+                    if image.exists() {
+                        let image_dest =
+                            json_dest.with_file_name(image.file_name().unwrap_or_default());
+                        labelme.imagePath = image_dest
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string();
+                        export_file(&image, &image_dest, ExportMode::Copy)?;
+                    }
+                    if let Some(parent) = json_dest.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&json_dest, serde_json::to_string_pretty(&labelme)?)?;
+                }
+                None if renamed + dropped > 0 => {
+                    fs::write(json, serde_json::to_string_pretty(&labelme)?)?;
+                }
+                None => {}
+            }
+            Ok((labels, renamed, dropped))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut report = RemapReport {
+        files_checked: all_json.len(),
+        ..Default::default()
+    };
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    for (labels, renamed, dropped) in results.into_iter() {
+        if renamed + dropped > 0 {
+            report.files_changed += 1;
+        }
+        report.shapes_renamed += renamed;
+        report.shapes_dropped += dropped;
+        seen.extend(labels);
+    }
+    for label in seen.iter() {
+        report
+            .mapping
+            .insert(label.to_owned(), class_map.map_label(label));
+    }
+    report.classes = class_map.class_order(seen.iter());
+    Ok(report)
+}
+
+/// remaps a yolo (or yolo-seg) dataset: rewrites the class ids of
+/// every txt and the names of its data.yaml, in place or into `options.output`
+/// together with the images
+pub fn remap_yolo_folder(
+    folder: &Path,
+    class_map: &ClassMap,
+    options: &RemapOptions,
+) -> Result<RemapReport, Error> {
+    let data_yaml_path = match &options.data_yaml {
+        Some(data_yaml) => data_yaml.to_owned(),
+        None => folder.join("data.yaml"),
+    };
+    if !data_yaml_path.exists() {
+        bail!(
+            "[error]::class_map: yolo datasets need a data.yaml with the class names, {:?} not found",
+            data_yaml_path
+        );
+    }
+    let mut data_yaml = read_data_yaml(&data_yaml_path)?;
+    let classes = class_map.class_order(data_yaml.names.iter());
+    // old index -> new index
+    let new_index: Vec<Option<usize>> = data_yaml
+        .names
+        .iter()
+        .map(|name| {
+            class_map
+                .map_label(name)
+                .and_then(|mapped| classes.iter().position(|c| *c == mapped))
+        })
+        .collect();
+
+    let mut discovery = options.discovery.to_owned();
+    discovery.skip_folders.extend(options.output.to_owned());
+    let all_txt: Vec<PathBuf> = find_files(folder, &["txt"], &discovery)?
+        .into_iter()
+        .filter(|txt| txt.file_name().unwrap_or_default() != "classes.txt")
+        .collect();
+    // every txt is remapped in memory first, a bad class id
+    // shouldn't leave the dataset half rewritten
+    let results = all_txt
+        .par_iter()
+        .map(|txt| -> Result<(usize, usize, String), Error> {
+            let (mut renamed, mut dropped) = (0, 0);
+            let mut lines: Vec<String> = vec![];
+            for line in fs::read_to_string(txt)?.lines() {
+                let mut tokens = line.split_whitespace();
+                let class_id = match tokens.next() {
+                    Some(class_id) => class_id,
+                    None => continue,
+                };
+                let old = match class_id.parse::<usize>() {
+                    Ok(old) if old < new_index.len() => old,
+                    _ => bail!(
+                        "[error]::class_map: {:?}: class id {} is not in {:?}",
+                        txt,
+                        class_id,
+                        data_yaml_path
+                    ),
+                };
+                match new_index[old] {
+                    Some(new) => {
+                        if new != old || classes[new] != data_yaml.names[old] {
+                            renamed += 1;
+                        }
+                        let rest: Vec<&str> = tokens.collect();
+                        lines.push(format!("{} {}", new, rest.join(" ")));
+                    }
+                    None => dropped += 1,
+                }
+            }
+            let mut contents = lines.join("\n");
+            if !contents.is_empty() {
+                contents.push('\n');
+            }
+            Ok((renamed, dropped, contents))
+        })
+        .collect::<Result<Vec<(usize, usize, String)>, Error>>()?;
+
+    let mut report = RemapReport {
+        files_checked: all_txt.len(),
+        classes: classes.to_owned(),
+        ..Default::default()
+    };
+    for (renamed, dropped, _) in results.iter() {
+        if renamed + dropped > 0 {
+            report.files_changed += 1;
+        }
+        report.shapes_renamed += renamed;
+        report.shapes_dropped += dropped;
+    }
+    for name in data_yaml.names.iter() {
+        report
+            .mapping
+            .insert(name.to_owned(), class_map.map_label(name));
+    }
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    all_txt
+        .par_iter()
+        .zip(results.par_iter())
+        .map(|(txt, (_, _, contents))| -> Result<(), Error> {
+            let dest = match &options.output {
+                Some(output) => output.join(relative_path(folder, txt)),
+                None => txt.to_owned(),
+            };
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(dest, contents)?;
+            Ok(())
+        })
+        .collect::<Result<(), Error>>()?;
+
+    data_yaml.names = classes.to_owned();
+    data_yaml.nc = classes.len() as i64;
+    match &options.output {
+        Some(output) => {
+            for image in find_files(folder, &IMAGE_EXTENSIONS, &discovery)?.iter() {
+                export_file(
+                    image,
+                    &output.join(relative_path(folder, image)),
+                    ExportMode::Copy,
+                )?;
+            }
+            // split folders inside the dataset move along with it,
+            // older data.yamls without `path` have them relative to the working dir
+            match &data_yaml.path {
+                Some(_) => {
+                    data_yaml.path =
+                        Some(std::path::absolute(output)?.to_string_lossy().to_string())
+                }
+                None => {
+                    let folder_str = folder.to_string_lossy().to_string();
+                    let output_str = output.to_string_lossy().to_string();
+                    for split in [
+                        &mut data_yaml.train,
+                        &mut data_yaml.val,
+                        &mut data_yaml.test,
+                    ] {
+                        if let Some(rest) = split.strip_prefix(&folder_str) {
+                            *split = format!("{}{}", output_str, rest);
+                        }
+                    }
+                }
+            }
+            fs::create_dir_all(output)?;
+            write_dataset_info(&data_yaml, output)?;
+        }
+        None => {
+            let mut yaml_file = fs::File::create(&data_yaml_path)?;
+            serde_yaml::to_writer(&mut yaml_file, &data_yaml)?;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test_class_map {
+    use crate::class_map::*;
+    use crate::fileutils::DatasetInfo;
+    use crate::label::read_labels_from_file;
+    use uuid::Uuid;
+
+    const CARDS_MAP: &str = r#"
+rules:
+  - from: "10C"
+    to: ten_clubs
+  - from: "*C"
+    to: club
+  - regex: "(\\d+)H"
+    to: "heart_$1"
+  - from: joker
+    drop: true
+order: [club]
+"#;
+
+    #[test]
+    fn map_labels() {
+        let _map = ClassMap::from_yaml(CARDS_MAP, Path::new("")).unwrap();
+        assert_eq!(_map.map_label("10C"), Some(String::from("ten_clubs")));
+        assert_eq!(_map.map_label("2C"), Some(String::from("club")));
+        assert_eq!(_map.map_label("7H"), Some(String::from("heart_7")));
+        assert_eq!(_map.map_label("joker"), None);
+        assert_eq!(_map.map_label("KS"), Some(String::from("KS")));
+        let _labels: Vec<String> = ["KS", "2C", "7H", "joker", "10C"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(
+            _map.class_order(_labels.iter()),
+            vec!["club", "KS", "heart_7", "ten_clubs"]
+        );
+
+        let _drop_rest = ClassMap::from_yaml(
+            "unmapped: drop\nrules: [{from: eye, to: eyes}]",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(_drop_rest.map_label("eye"), Some(String::from("eyes")));
+        assert_eq!(_drop_rest.map_label("ear"), None);
+        assert!(ClassMap::from_yaml("rules: [{from: a}]", Path::new("")).is_err());
+        assert!(ClassMap::from_yaml("rules: [{from: a, regex: b, to: c}]", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn remap_labelme_and_yolo() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(_tmp.join("labelme")).unwrap();
+        fs::copy("test/test.json", _tmp.join("labelme/test.json")).unwrap();
+        fs::copy("test/test.png", _tmp.join("labelme/test.png")).unwrap();
+        let _map = ClassMap::from_yaml(
+            "rules:\n  - {from: eye, to: face}\n  - {from: mouth, to: face}\n  - {from: ear, drop: true}",
+            Path::new(""),
+        )
+        .unwrap();
+        let mut _options = RemapOptions {
+            output: Some(_tmp.join("out")),
+            dry_run: false,
+            data_yaml: None,
+            discovery: DiscoveryOptions::default(),
+        };
+        let _report = remap_labelme_folder(&_tmp.join("labelme"), &_map, &_options).unwrap();
+        assert_eq!((_report.shapes_renamed, _report.shapes_dropped), (3, 1));
+        assert_eq!(_report.classes, vec!["face"]);
+        let _labelme = read_labels_from_file(_tmp.join("out/test.json").to_str().unwrap()).unwrap();
+        assert!(_labelme.shapes.iter().all(|s| s.label == "face"));
+        assert!(_tmp.join("out/test.png").exists());
+        // the source is untouched
+        let _source =
+            read_labels_from_file(_tmp.join("labelme/test.json").to_str().unwrap()).unwrap();
+        assert_eq!(_source.shapes.len(), 4);
+
+        fs::create_dir_all(_tmp.join("yolo/labels")).unwrap();
+        fs::write(
+            _tmp.join("yolo/labels/a.txt"),
+            "0 0.5 0.5 0.1 0.1\n1 0.2 0.2 0.1 0.1\n2 0.3 0.3 0.1 0.1\n",
+        )
+        .unwrap();
+        let _data = DatasetInfo {
+            path: None,
+            names: vec![
+                String::from("ear"),
+                String::from("eye"),
+                String::from("mouth"),
+            ],
+            nc: 3,
+            train: String::new(),
+            val: String::new(),
+            test: String::new(),
+        };
+        write_dataset_info(&_data, &_tmp.join("yolo")).unwrap();
+        _options.output = None;
+        let _report = remap_yolo_folder(&_tmp.join("yolo"), &_map, &_options).unwrap();
+        assert_eq!((_report.shapes_renamed, _report.shapes_dropped), (2, 1));
+        assert_eq!(
+            fs::read_to_string(_tmp.join("yolo/labels/a.txt")).unwrap(),
+            "0 0.2 0.2 0.1 0.1\n0 0.3 0.3 0.1 0.1\n"
+        );
+        assert_eq!(
+            read_data_yaml(&_tmp.join("yolo/data.yaml")).unwrap().names,
+            vec!["face"]
+        );
+
+        // a bad class id leaves every file as it was
+        write_dataset_info(&_data, &_tmp.join("yolo")).unwrap();
+        fs::write(_tmp.join("yolo/labels/a.txt"), "1 0.2 0.2 0.1 0.1\n").unwrap();
+        fs::write(_tmp.join("yolo/labels/b.txt"), "7 0.2 0.2 0.1 0.1\n").unwrap();
+        assert!(remap_yolo_folder(&_tmp.join("yolo"), &_map, &_options).is_err());
+        assert_eq!(
+            fs::read_to_string(_tmp.join("yolo/labels/a.txt")).unwrap(),
+            "1 0.2 0.2 0.1 0.1\n"
+        );
+        assert_eq!(
+            read_data_yaml(&_tmp.join("yolo/data.yaml")).unwrap().names,
+            _data.names
+        );
+        fs::remove_dir_all(_tmp).unwrap();
+    }
+}
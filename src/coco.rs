@@ -0,0 +1,346 @@
+/* coco `instances_*.json` import and export */
+use crate::fileutils::{open_image, relative_path, write_labelme_to_json};
+use crate::image_utils::dynimg2string;
+use crate::label::{
+    polygon_area, read_labels_from_file, skip_unsupported, LabelmeAnnotation, Shape, ShapeType,
+};
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// flag used in `Shape::flags` to mark coco crowd regions
+pub const ISCROWD_FLAG: &str = "iscrowd";
+
+/// a whole coco file, only the parts we care about,
+/// `info` and `licenses` are kept as is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CocoDataset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub licenses: Option<serde_json::Value>,
+    pub images: Vec<CocoImage>,
+    pub annotations: Vec<CocoAnnotation>,
+    pub categories: Vec<CocoCategory>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CocoImage {
+    pub id: i64,
+    pub file_name: String,
+    pub width: i64,
+    pub height: i64,
+}
+
+/// `bbox` is `[x, y, w, h]` in pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CocoAnnotation {
+    pub id: i64,
+    pub image_id: i64,
+    pub category_id: i64,
+    pub bbox: Vec<f32>,
+    #[serde(default)]
+    pub area: f32,
+    #[serde(default)]
+    pub segmentation: CocoSegmentation,
+    #[serde(default)]
+    pub iscrowd: i64,
+}
+
+/// polygons are `[[x1, y1, x2, y2, ...], ...]`,
+/// crowd regions usually come as run length encoding instead
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CocoSegmentation {
+    Polygons(Vec<Vec<f32>>),
+    Rle {
+        counts: serde_json::Value,
+        size: Vec<i64>,
+    },
+}
+
+impl Default for CocoSegmentation {
+    fn default() -> Self {
+        CocoSegmentation::Polygons(vec![])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CocoCategory {
+    pub id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub supercategory: String,
+}
+
+impl CocoDataset {
+    /// builds a coco dataset from labelme jsons,
+    /// category ids follow `all_classes` order starting from 1.
+    /// shapes without area (points, lines) are skipped.
+    /// `file_name`s are relative to `folder`
+    pub fn from_labelme(
+        folder: &Path,
+        all_jsons: &[PathBuf],
+        all_classes: &[String],
+    ) -> Result<CocoDataset, Error> {
+        let mut sorted_jsons = all_jsons.to_vec();
+        sorted_jsons.sort();
+        let all_labelme = sorted_jsons
+            .iter()
+            .map(|json| {
+                let mut labelme = read_labels_from_file(json.to_str().unwrap())?;
+                // `imagePath` is relative to the json, which may sit in a subfolder
+                let image_path = json
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join(&labelme.imagePath);
+                labelme.imagePath = relative_path(folder, &image_path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                Ok(labelme)
+            })
+            .collect::<Result<Vec<LabelmeAnnotation>, Error>>()?;
+        CocoDataset::from_annotations(&all_labelme, all_classes)
+    }
+
+    /// same as `from_labelme` for annotations already in memory,
+    /// `imagePath` becomes the coco `file_name`
+    pub fn from_annotations(
+        all_labelme: &[LabelmeAnnotation],
+        all_classes: &[String],
+    ) -> Result<CocoDataset, Error> {
+        let categories: Vec<CocoCategory> = all_classes
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| CocoCategory {
+                id: idx as i64 + 1,
+                name: name.to_owned(),
+                supercategory: String::new(),
+            })
+            .collect();
+        let category_ids: HashMap<&String, i64> =
+            categories.iter().map(|c| (&c.name, c.id)).collect();
+
+        let mut images: Vec<CocoImage> = vec![];
+        let mut annotations: Vec<CocoAnnotation> = vec![];
+        for (img_idx, labelme) in all_labelme.iter().enumerate() {
+            let image_id = img_idx as i64 + 1;
+            images.push(CocoImage {
+                id: image_id,
+                file_name: labelme.imagePath.to_owned(),
+                width: labelme.imageWidth,
+                height: labelme.imageHeight,
+            });
+            for shape in labelme.shapes.iter() {
+                if skip_unsupported(shape) || !shape.shape_type.has_area() {
+                    continue;
+                }
+                let category_id = match category_ids.get(&shape.label) {
+                    Some(id) => *id,
+                    None => bail!(
+                        "[error]::coco: label {:?} in {:?} is not in the class list",
+                        &shape.label,
+                        &labelme.imagePath
+                    ),
+                };
+                let bbox = shape.bbox()?;
+                let polygon = shape.polygon()?;
+                let iscrowd = shape.flag_is_set(ISCROWD_FLAG) as i64;
+                annotations.push(CocoAnnotation {
+                    id: annotations.len() as i64 + 1,
+                    image_id,
+                    category_id,
+                    bbox: vec![bbox.x1, bbox.y1, bbox.x2 - bbox.x1, bbox.y2 - bbox.y1],
+                    area: polygon_area(&polygon),
+                    segmentation: CocoSegmentation::Polygons(vec![polygon
+                        .into_iter()
+                        .flatten()
+                        .collect()]),
+                    iscrowd,
+                });
+            }
+        }
+        Ok(CocoDataset {
+            info: None,
+            licenses: None,
+            images,
+            annotations,
+            categories,
+        })
+    }
+
+    /// one labelme annotation per coco image.
+    ///
+    /// every polygon of a segmentation becomes its own shape,
+    /// crowd regions stored as rle fall back to their bbox.
+    /// crowd shapes are marked with the `iscrowd` flag
+    pub fn to_labelme(&self) -> Result<Vec<LabelmeAnnotation>, Error> {
+        let category_names: HashMap<i64, &String> =
+            self.categories.iter().map(|c| (c.id, &c.name)).collect();
+        let mut shapes_per_image: HashMap<i64, Vec<Shape>> = HashMap::new();
+        for anno in self.annotations.iter() {
+            let label = match category_names.get(&anno.category_id) {
+                Some(name) => name.to_string(),
+                None => bail!(
+                    "[error]::coco: annotation {} has unknown category {}",
+                    anno.id,
+                    anno.category_id
+                ),
+            };
+            let mut flags: HashMap<String, String> = HashMap::new();
+            if anno.iscrowd != 0 {
+                flags.insert(String::from(ISCROWD_FLAG), String::from("true"));
+            }
+            let polygons: Vec<&Vec<f32>> = match &anno.segmentation {
+                CocoSegmentation::Polygons(polygons) => {
+                    polygons.iter().filter(|p| p.len() >= 6).collect()
+                }
+                CocoSegmentation::Rle { .. } => vec![],
+            };
+            let shapes = shapes_per_image.entry(anno.image_id).or_default();
+            if polygons.is_empty() {
+                if anno.bbox.len() != 4 {
+                    bail!("[error]::coco: annotation {} has an invalid bbox", anno.id);
+                }
+                let (x, y, w, h) = (anno.bbox[0], anno.bbox[1], anno.bbox[2], anno.bbox[3]);
+                shapes.push(Shape {
+                    label,
+                    points: vec![vec![x, y], vec![x + w, y + h]],
+                    group_id: None,
+                    shape_type: ShapeType::Rectangle,
+                    flags: Some(flags),
+                    extra: serde_json::Map::new(),
+                });
+            } else {
+                for polygon in polygons {
+                    let mut shape = Shape {
+                        label: label.to_owned(),
+                        points: polygon.chunks_exact(2).map(|p| p.to_vec()).collect(),
+                        group_id: None,
+                        shape_type: ShapeType::Polygon,
+                        flags: Some(flags.to_owned()),
+                        extra: serde_json::Map::new(),
+                    };
+                    // rectangles exported by kesa come back as rectangles
+                    let bbox = shape.bbox()?;
+                    let is_rectangle = shape.points.len() == 4
+                        && shape.points.iter().all(|p| {
+                            (p[0] == bbox.x1 || p[0] == bbox.x2)
+                                && (p[1] == bbox.y1 || p[1] == bbox.y2)
+                        });
+                    if is_rectangle {
+                        shape.shape_type = ShapeType::Rectangle;
+                        shape.update_points_from_xyxy(bbox);
+                    }
+                    shapes.push(shape);
+                }
+            }
+        }
+
+        Ok(self
+            .images
+            .iter()
+            .map(|image| {
+                LabelmeAnnotation::new(
+                    Some(HashMap::new()),
+                    shapes_per_image.remove(&image.id).unwrap_or_default(),
+                    image.file_name.to_owned(),
+                    None,
+                    image.width,
+                    image.height,
+                )
+            })
+            .collect())
+    }
+}
+
+pub fn read_coco_from_file(filename: &Path) -> Result<CocoDataset, Error> {
+    let coco_str = fs::read_to_string(filename)?;
+    let coco: CocoDataset = serde_json::from_str(&coco_str)?;
+    Ok(coco)
+}
+
+pub fn write_coco_to_json(coco: &CocoDataset, filename: &Path) -> Result<(), Error> {
+    let mut coco_file = fs::File::create(filename)?;
+    serde_json::to_writer(&mut coco_file, coco)?;
+    Ok(())
+}
+
+/// writes a labelme json next to every image of a coco file,
+/// `image_folder` is the folder `file_name` is relative to.
+/// returns the amount of jsons written
+pub fn write_coco_to_labelme(
+    coco: &CocoDataset,
+    image_folder: &Path,
+    embed_image_data: bool,
+) -> Result<usize, Error> {
+    let all_labelme = coco.to_labelme()?;
+    for labelme in all_labelme.iter() {
+        let image_path = image_folder.join(&labelme.imagePath);
+        let mut labelme = labelme.to_owned();
+        // labelme wants the image path relative to the json
+        labelme.imagePath = image_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        if embed_image_data {
+            labelme.imageData = Some(dynimg2string(&open_image(&image_path)?)?);
+        }
+        write_labelme_to_json(&labelme, &image_path)?;
+    }
+    Ok(all_labelme.len())
+}
+
+#[cfg(test)]
+mod test_coco {
+    use crate::coco::*;
+    use crate::fileutils::{get_all_classes, get_all_jsons};
+
+    #[test]
+    fn coco_round_trip() {
+        let _all_json = get_all_jsons("test").unwrap();
+        let _all_classes = get_all_classes(&_all_json).unwrap();
+        let _coco =
+            CocoDataset::from_labelme(Path::new("test"), &_all_json, &_all_classes).unwrap();
+        assert_eq!(_coco.images.len(), 1);
+        assert_eq!(_coco.annotations.len(), 4);
+        assert_eq!(_coco.categories[0].id, 1);
+        assert_eq!(_coco.images[0].file_name, "test.png");
+
+        let _serialized = serde_json::to_string(&_coco).unwrap();
+        let _deserialized: CocoDataset = serde_json::from_str(&_serialized).unwrap();
+        assert_eq!(_coco, _deserialized);
+
+        let _labelme = _coco.to_labelme().unwrap();
+        let _orig = read_labels_from_file("test/test.json").unwrap();
+        assert_eq!(_labelme[0].shapes.len(), 4);
+        assert_eq!(_labelme[0].shapes[0].shape_type, ShapeType::Rectangle);
+        let _bbox = _labelme[0].shapes[0].bbox().unwrap();
+        let _orig_bbox = _orig.shapes[0].bbox().unwrap();
+        assert!((_bbox.x1 - _orig_bbox.x1).abs() < 1e-3);
+        assert!((_bbox.y2 - _orig_bbox.y2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn coco_crowd_rle() {
+        let _coco: CocoDataset = serde_json::from_str(
+            r#"{"images": [{"id": 1, "file_name": "a.png", "width": 10, "height": 10}],
+                "annotations": [{"id": 1, "image_id": 1, "category_id": 3, "bbox": [1, 2, 3, 4],
+                                 "area": 12, "iscrowd": 1,
+                                 "segmentation": {"counts": [0, 12, 88], "size": [10, 10]}}],
+                "categories": [{"id": 3, "name": "card"}]}"#,
+        )
+        .unwrap();
+        let _labelme = _coco.to_labelme().unwrap();
+        let _shape = &_labelme[0].shapes[0];
+        assert_eq!(_shape.shape_type, ShapeType::Rectangle);
+        assert_eq!(_shape.points, vec![vec![1.0, 2.0], vec![4.0, 6.0]]);
+        assert_eq!(
+            _shape.flags.as_ref().unwrap().get(ISCROWD_FLAG).unwrap(),
+            "true"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+/* in-memory dataset shared by every annotation format */
+use crate::label::{LabelmeAnnotation, Shape};
+use anyhow::{Error, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// every image of a dataset with its shapes,
+/// `classes` is the class map, the index is the class id
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    /// folder `DatasetItem::image_path` is relative to
+    pub root: PathBuf,
+    pub classes: Vec<String>,
+    pub items: Vec<DatasetItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetItem {
+    /// relative to `Dataset::root`
+    pub image_path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// screen coordinates
+    pub shapes: Vec<Shape>,
+    pub flags: HashMap<String, String>,
+    /// `train`, `val` or `test` when the source was already split
+    pub split: Option<String>,
+    /// image to read instead of `root/image_path`, e.g. when
+    /// items of several datasets are merged into one
+    pub source: Option<PathBuf>,
+}
+
+impl Dataset {
+    pub fn new(root: &Path) -> Dataset {
+        Dataset {
+            root: root.to_path_buf(),
+            classes: vec![],
+            items: vec![],
+        }
+    }
+
+    /// index of a class, added to the end of the class map if missing
+    pub fn class_index(&mut self, name: &str) -> i64 {
+        match self.classes.iter().position(|c| c == name) {
+            Some(idx) => idx as i64,
+            None => {
+                self.classes.push(String::from(name));
+                self.classes.len() as i64 - 1
+            }
+        }
+    }
+
+    /// same as `fileutils::get_all_classes_hash`
+    pub fn class_hash(&self) -> HashMap<String, i64> {
+        self.classes
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.to_owned(), idx as i64))
+            .collect()
+    }
+
+    /// the class map plus every label used by the shapes that
+    /// isn't in it yet, sorted like `fileutils::get_all_classes`
+    pub fn all_classes(&self) -> Vec<String> {
+        let mut new_classes: Vec<String> = self
+            .items
+            .iter()
+            .flat_map(|item| item.shapes.iter().map(|s| s.label.to_owned()))
+            .filter(|label| !self.classes.contains(label))
+            .collect();
+        new_classes.sort();
+        new_classes.dedup();
+        let mut all_classes = self.classes.to_owned();
+        all_classes.extend(new_classes);
+        all_classes
+    }
+
+    /// adds every label used by the shapes to the class map
+    pub fn collect_classes(&mut self) {
+        self.classes = self.all_classes();
+    }
+
+    /// absolute path of an item's image
+    pub fn image_source(&self, item: &DatasetItem) -> PathBuf {
+        match &item.source {
+            Some(source) => source.to_owned(),
+            None => self.root.join(&item.image_path),
+        }
+    }
+}
+
+impl DatasetItem {
+    pub fn from_labelme(image_path: &Path, labelme: &LabelmeAnnotation) -> DatasetItem {
+        DatasetItem {
+            image_path: image_path.to_path_buf(),
+            width: labelme.imageWidth as u32,
+            height: labelme.imageHeight as u32,
+            shapes: labelme.shapes.to_owned(),
+            flags: labelme.flags.to_owned().unwrap_or_default(),
+            split: None,
+            source: None,
+        }
+    }
+
+    /// imagePath is only the file name, labelme jsons
+    /// are written next to their image
+    pub fn to_labelme(&self) -> LabelmeAnnotation {
+        LabelmeAnnotation::new(
+            Some(self.flags.to_owned()),
+            self.shapes.to_owned(),
+            self.image_path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            None,
+            self.width as i64,
+            self.height as i64,
+        )
+    }
+}
+
+/// copies an image into the output tree,
+/// does nothing if source and destination are the same file
+pub fn copy_image(source: &Path, destination: &Path) -> Result<(), Error> {
+    if let (Ok(src), Ok(dst)) = (fs::canonicalize(source), fs::canonicalize(destination)) {
+        if src == dst {
+            return Ok(());
+        }
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, destination)?;
+    Ok(())
+}
@@ -0,0 +1,377 @@
+/* exact and perceptual duplicate detection for image folders */
+use crate::fileutils::{
+    content_hash, export_file, find_files, get_txt_from_image, relative_path, DiscoveryOptions,
+    ExportMode, IMAGE_EXTENSIONS,
+};
+use crate::label::LabelmeAnnotation;
+use anyhow::{Error, Result};
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// label files next to an image that move along with it
+const SIDECAR_EXTENSIONS: [&str; 3] = ["json", "txt", "xml"];
+
+/// cluster file `tag` writes into the folder, read by `--group-clusters`
+pub const DEDUP_CLUSTERS_NAME: &str = "dedup_clusters.yaml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum DedupMode {
+    /// only print / write the clusters
+    #[default]
+    Report,
+    /// move every image but the first of a cluster (and its labels) aside
+    Move,
+    /// write the clusters into a cluster file (`--group-clusters`) and into
+    /// a labelme flag of every member (`--group-flag`), both keep a cluster in one split
+    Tag,
+}
+
+#[derive(Debug, Clone)]
+pub struct DedupOptions {
+    /// max differing bits of the 64 bit dHash to count as near duplicates,
+    /// 0 only matches images that look the same after downscaling
+    pub threshold: u32,
+    pub mode: DedupMode,
+    /// where `move` puts the duplicates
+    pub duplicates_folder: PathBuf,
+    /// flag written by `tag`
+    pub flag: String,
+    /// cluster file written by `tag`
+    pub clusters_file: PathBuf,
+    pub discovery: DiscoveryOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageHashes {
+    pub image: PathBuf,
+    /// sha256 of the file
+    pub content: String,
+    /// none if the image couldn't be decoded, it then only matches byte for byte
+    pub dhash: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DedupCluster {
+    /// the first image is the one that's kept
+    pub images: Vec<String>,
+    /// every image of the cluster is the same file byte for byte
+    pub exact: bool,
+    /// largest dHash distance to the first image
+    pub max_distance: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DedupReport {
+    pub images: usize,
+    /// images that are in a cluster but aren't its first image
+    pub duplicates: usize,
+    pub clusters: Vec<DedupCluster>,
+    /// images `move` moved aside
+    pub moved: usize,
+    /// jsons `tag` wrote the cluster flag to
+    pub tagged: usize,
+    /// cluster members without a labelme json, only in the cluster file
+    pub untagged: Vec<String>,
+    /// images that couldn't be decoded, left out of the perceptual hashing
+    pub unreadable: Vec<String>,
+}
+
+/// difference hash, one bit per neighbouring pixel pair of a
+/// 9x8 grayscale thumbnail (set when the left one is brighter)
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let thumbnail = image
+        .grayscale()
+        .resize_exact(9, 8, FilterType::Triangle)
+        .to_luma8();
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if thumbnail.get_pixel(x, y)[0] > thumbnail.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn find_root(parents: &mut [usize], idx: usize) -> usize {
+    let mut root = idx;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    // path compression
+    let mut current = idx;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+/// groups images with the same content or a dHash distance
+/// up to `threshold`, only clusters with more than one image are returned.
+/// near duplicates chain, a ~ b and b ~ c puts a, b and c together
+pub fn cluster_images(hashes: &[ImageHashes], threshold: u32) -> Vec<Vec<usize>> {
+    let pairs: Vec<(usize, usize)> = (0..hashes.len())
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            (i + 1..hashes.len())
+                .filter(move |j| {
+                    hashes[i].content == hashes[*j].content
+                        || matches!(
+                            (hashes[i].dhash, hashes[*j].dhash),
+                            (Some(a), Some(b)) if (a ^ b).count_ones() <= threshold
+                        )
+                })
+                .map(move |j| (i, j))
+        })
+        .collect();
+    let mut parents: Vec<usize> = (0..hashes.len()).collect();
+    for (i, j) in pairs.into_iter() {
+        let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+        if root_i != root_j {
+            parents[root_i.max(root_j)] = root_i.min(root_j);
+        }
+    }
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for idx in 0..hashes.len() {
+        let root = find_root(&mut parents, idx);
+        clusters.entry(root).or_default().push(idx);
+    }
+    clusters
+        .into_values()
+        .filter(|cluster| cluster.len() > 1)
+        .collect()
+}
+
+/// finds the duplicate images of a folder and reports, moves or tags them
+pub fn dedup_folder(folder: &Path, options: &DedupOptions) -> Result<DedupReport, Error> {
+    let mut discovery = options.discovery.to_owned();
+    discovery
+        .skip_folders
+        .push(options.duplicates_folder.to_owned());
+    let images = find_files(folder, &IMAGE_EXTENSIONS, &discovery)?;
+    let hashes = images
+        .par_iter()
+        .map(|image| -> Result<ImageHashes, Error> {
+            Ok(ImageHashes {
+                image: image.to_owned(),
+                content: content_hash(image)?,
+                dhash: image::open(image).ok().map(|decoded| dhash(&decoded)),
+            })
+        })
+        .collect::<Result<Vec<ImageHashes>, Error>>()?;
+
+    let mut report = DedupReport {
+        images: images.len(),
+        unreadable: hashes
+            .iter()
+            .filter(|hashes| hashes.dhash.is_none())
+            .map(|hashes| hashes.image.to_string_lossy().to_string())
+            .collect(),
+        ..Default::default()
+    };
+    let clusters = cluster_images(&hashes, options.threshold);
+    // cluster -> images relative to the folder
+    let mut cluster_file: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (cluster_idx, cluster) in clusters.iter().enumerate() {
+        let first = &hashes[cluster[0]];
+        report.duplicates += cluster.len() - 1;
+        report.clusters.push(DedupCluster {
+            images: cluster
+                .iter()
+                .map(|idx| hashes[*idx].image.to_string_lossy().to_string())
+                .collect(),
+            exact: cluster
+                .iter()
+                .all(|idx| hashes[*idx].content == first.content),
+            max_distance: cluster
+                .iter()
+                .filter_map(|idx| Some((hashes[*idx].dhash? ^ first.dhash?).count_ones()))
+                .max()
+                .unwrap_or(0),
+        });
+        match options.mode {
+            DedupMode::Report => {}
+            DedupMode::Move => {
+                for idx in cluster.iter().skip(1) {
+                    move_aside(&hashes[*idx].image, folder, &options.duplicates_folder)?;
+                    report.moved += 1;
+                }
+            }
+            DedupMode::Tag => {
+                for idx in cluster.iter() {
+                    let image = &hashes[*idx].image;
+                    cluster_file
+                        .entry(format!("dup_{:05}", cluster_idx))
+                        .or_default()
+                        .push(relative_path(folder, image).to_string_lossy().to_string());
+                    let json = image.with_extension("json");
+                    if !json.exists() {
+                        report.untagged.push(image.to_string_lossy().to_string());
+                        continue;
+                    }
+                    let mut labelme: LabelmeAnnotation =
+                        serde_json::from_str(&fs::read_to_string(&json)?)?;
+                    labelme
+                        .flags
+                        .get_or_insert_with(Default::default)
+                        .insert(options.flag.to_owned(), format!("dup_{:05}", cluster_idx));
+                    fs::write(&json, serde_json::to_string_pretty(&labelme)?)?;
+                    report.tagged += 1;
+                }
+            }
+        }
+    }
+    if options.mode == DedupMode::Tag {
+        fs::write(
+            &options.clusters_file,
+            serde_yaml::to_string(&cluster_file)?,
+        )?;
+    }
+    Ok(report)
+}
+
+/// label files of an image, the ones next to it and the yolo
+/// txt in `labels` for an image in `images`
+pub fn label_files_of(image: &Path) -> Vec<PathBuf> {
+    let mut labels: Vec<PathBuf> = SIDECAR_EXTENSIONS
+        .iter()
+        .map(|ext| image.with_extension(ext))
+        .collect();
+    let txt = get_txt_from_image(image);
+    if !labels.contains(&txt) {
+        labels.push(txt);
+    }
+    labels.into_iter().filter(|label| label.is_file()).collect()
+}
+
+/// moves an image and its label files into `duplicates_folder`,
+/// keeping their paths relative to `folder`
+fn move_aside(image: &Path, folder: &Path, duplicates_folder: &Path) -> Result<(), Error> {
+    for label in label_files_of(image) {
+        let dest = duplicates_folder.join(relative_path(folder, &label));
+        export_file(&label, &dest, ExportMode::Move)?;
+    }
+    let dest = duplicates_folder.join(relative_path(folder, image));
+    export_file(image, &dest, ExportMode::Move)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_dedup {
+    use crate::dedup::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn exact_and_near_duplicates() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(_tmp.join("scrape")).unwrap();
+        let _image = image::open("test/test.png").unwrap();
+        _image.save(_tmp.join("scrape/a.png")).unwrap();
+        fs::copy(_tmp.join("scrape/a.png"), _tmp.join("scrape/b.png")).unwrap();
+        _image.brighten(12).save(_tmp.join("scrape/c.png")).unwrap();
+        _image.fliph().save(_tmp.join("scrape/d.png")).unwrap();
+        fs::write(_tmp.join("scrape/e.png"), "not an image").unwrap();
+        fs::write(_tmp.join("scrape/f.png"), "not an image").unwrap();
+        fs::copy("test/test.json", _tmp.join("scrape/c.json")).unwrap();
+
+        let mut _options = DedupOptions {
+            threshold: 4,
+            mode: DedupMode::Report,
+            duplicates_folder: _tmp.join("scrape/duplicates"),
+            flag: String::from("dedup_cluster"),
+            clusters_file: _tmp.join("scrape").join(DEDUP_CLUSTERS_NAME),
+            discovery: DiscoveryOptions::default(),
+        };
+        let _report = dedup_folder(&_tmp.join("scrape"), &_options).unwrap();
+        assert_eq!(_report.images, 6);
+        assert_eq!(_report.unreadable.len(), 2);
+        assert_eq!(_report.clusters.len(), 2);
+        let _names = |cluster: &DedupCluster| -> Vec<String> {
+            cluster
+                .images
+                .iter()
+                .map(|i| {
+                    Path::new(i)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        };
+        assert_eq!(
+            _names(&_report.clusters[0]),
+            vec!["a.png", "b.png", "c.png"]
+        );
+        assert!(!_report.clusters[0].exact);
+        assert_eq!(_names(&_report.clusters[1]), vec!["e.png", "f.png"]);
+        assert!(_report.clusters[1].exact);
+
+        _options.mode = DedupMode::Tag;
+        let _report = dedup_folder(&_tmp.join("scrape"), &_options).unwrap();
+        assert_eq!(_report.tagged, 1);
+        let _labelme: LabelmeAnnotation =
+            serde_json::from_str(&fs::read_to_string(_tmp.join("scrape/c.json")).unwrap()).unwrap();
+        assert_eq!(_labelme.flags.unwrap()["dedup_cluster"], "dup_00000");
+        let _clusters: BTreeMap<String, Vec<String>> =
+            serde_yaml::from_str(&fs::read_to_string(&_options.clusters_file).unwrap()).unwrap();
+        assert_eq!(_clusters["dup_00001"], vec!["e.png", "f.png"]);
+
+        _options.mode = DedupMode::Move;
+        let _report = dedup_folder(&_tmp.join("scrape"), &_options).unwrap();
+        assert_eq!(_report.moved, 3);
+        assert!(_tmp.join("scrape/duplicates/c.json").exists());
+        assert!(!_tmp.join("scrape/c.png").exists());
+        // the duplicates folder isn't searched again
+        let _report = dedup_folder(&_tmp.join("scrape"), &_options).unwrap();
+        assert_eq!((_report.images, _report.duplicates), (3, 0));
+        fs::remove_dir_all(_tmp).unwrap();
+    }
+
+    #[test]
+    fn yolo_labels_move_along() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(_tmp.join("train/images")).unwrap();
+        fs::create_dir_all(_tmp.join("train/labels")).unwrap();
+        for _name in ["a", "b"] {
+            fs::copy(
+                "test/test.png",
+                _tmp.join(format!("train/images/{}.png", _name)),
+            )
+            .unwrap();
+            fs::write(
+                _tmp.join(format!("train/labels/{}.txt", _name)),
+                "0 0.5 0.5 0.1 0.1\n",
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            label_files_of(&_tmp.join("train/images/b.png")),
+            vec![_tmp.join("train/labels/b.txt")]
+        );
+        let _options = DedupOptions {
+            threshold: 0,
+            mode: DedupMode::Move,
+            duplicates_folder: _tmp.join("duplicates"),
+            flag: String::from("dedup_cluster"),
+            clusters_file: _tmp.join(DEDUP_CLUSTERS_NAME),
+            discovery: DiscoveryOptions::default(),
+        };
+        let _report = dedup_folder(&_tmp, &_options).unwrap();
+        assert_eq!(_report.moved, 1);
+        assert!(_tmp.join("duplicates/train/images/b.png").exists());
+        assert!(_tmp.join("duplicates/train/labels/b.txt").exists());
+        assert!(!_tmp.join("train/labels/b.txt").exists());
+        fs::remove_dir_all(_tmp).unwrap();
+    }
+}
@@ -1,21 +1,38 @@
-use crate::label::{read_labels_from_file, LabelmeAnnotation, YoloAnnotation};
-use anyhow::{Error, Result};
+use crate::label::{
+    read_labels_from_file, LabelmeAnnotation, YoloAnnotation, YoloLabel, YoloSegAnnotation,
+};
+use anyhow::{bail, Error, Result};
+use clap::{ArgAction, Args, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use image::DynamicImage;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{self, Value};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::fs::read_dir;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
+/// data.yaml in the ultralytics layout, `train`/`val`/`test` are relative to `path`.
+/// `names` are written as an index -> name map, the older list is read as well
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DatasetInfo {
-    pub names: Vec<String>,
-    pub nc: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default)]
     pub train: String,
+    #[serde(default)]
     pub val: String,
+    #[serde(default)]
     pub test: String,
+    #[serde(default)]
+    pub nc: i64,
+    #[serde(
+        serialize_with = "names_to_map",
+        deserialize_with = "names_from_list_or_map"
+    )]
+    pub names: Vec<String>,
 }
 
 // creates a data.yaml strucc
@@ -25,15 +42,43 @@ impl DatasetInfo {
         all_classes: &Vec<String>,
     ) -> Result<DatasetInfo, Error> {
         Ok(DatasetInfo {
-            names: all_classes.to_owned(),
+            path: Some(export_options.export_folder.to_string_lossy().to_string()),
+            train: String::from("train/images"),
+            val: String::from("val/images"),
+            test: String::from("test/images"),
             nc: all_classes.len().to_owned() as i64,
-            train: export_options.train_img.to_owned(),
-            val: export_options.val_img.to_owned(),
-            test: export_options.test_img.to_owned(),
+            names: all_classes.to_owned(),
         })
     }
 }
 
+fn names_to_map<S: serde::Serializer>(names: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(names.iter().enumerate())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ClassNames {
+    List(Vec<String>),
+    Map(BTreeMap<usize, String>),
+}
+
+fn names_from_list_or_map<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    match ClassNames::deserialize(deserializer)? {
+        ClassNames::List(names) => Ok(names),
+        ClassNames::Map(names) => {
+            if names.keys().enumerate().any(|(idx, key)| idx != *key) {
+                return Err(serde::de::Error::custom(
+                    "names map has to be numbered 0, 1, 2, ... without gaps",
+                ));
+            }
+            Ok(names.into_values().collect())
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExportFolderOptions {
     pub train_img: String,
@@ -45,18 +90,12 @@ pub struct ExportFolderOptions {
     pub test_img: String,
     pub test_label: String,
 
-    // yoooo
-    pub train_ratio: f32,
-    pub val_ratio: f32,
-    pub test_ratio: f32,
     pub export_folder: PathBuf,
 }
 
 impl ExportFolderOptions {
-    pub fn new(export_path: &str, split_train_val: f32) -> Result<ExportFolderOptions, Error> {
-        let split_rem = 1.0 - split_train_val;
-        let split_test = split_rem / 3.0;
-        let split_val = split_rem - split_test;
+    /// batch sizes are up to `split::SplitArguments`
+    pub fn new(export_path: &str) -> Result<ExportFolderOptions, Error> {
         Ok(ExportFolderOptions {
             train_img: format!("{}/train/images", &export_path),
             train_label: format!("{}/train/labels", &export_path),
@@ -64,13 +103,47 @@ impl ExportFolderOptions {
             val_label: format!("{}/val/labels", &export_path),
             test_img: format!("{}/test/images", &export_path),
             test_label: format!("{}/test/labels", &export_path),
-            train_ratio: split_train_val,
-            val_ratio: split_val,
-            test_ratio: split_test,
-            export_folder: fs::canonicalize(export_path).unwrap(),
+            export_folder: std::path::absolute(export_path)?,
+        })
+    }
+
+    /// darknet keeps images and their txts side by side in `<export>/<batch>`
+    pub fn darknet(export_path: &str) -> Result<ExportFolderOptions, Error> {
+        Ok(ExportFolderOptions {
+            train_img: format!("{}/train", &export_path),
+            train_label: format!("{}/train", &export_path),
+            val_img: format!("{}/val", &export_path),
+            val_label: format!("{}/val", &export_path),
+            test_img: format!("{}/test", &export_path),
+            test_label: format!("{}/test", &export_path),
+            export_folder: std::path::absolute(export_path)?,
         })
     }
 
+    /// the folder layout that goes with a data format
+    pub fn for_format(export_path: &str, format: DataFormat) -> Result<ExportFolderOptions, Error> {
+        match format {
+            DataFormat::Ultralytics => ExportFolderOptions::new(export_path),
+            DataFormat::Darknet => ExportFolderOptions::darknet(export_path),
+        }
+    }
+
+    /// image and label folder of a batch (`train`, `val` or `test`)
+    pub fn batch_folders(&self, batch: &str) -> Result<(PathBuf, PathBuf), Error> {
+        match batch {
+            "train" => Ok((
+                PathBuf::from(&self.train_img),
+                PathBuf::from(&self.train_label),
+            )),
+            "val" => Ok((PathBuf::from(&self.val_img), PathBuf::from(&self.val_label))),
+            "test" => Ok((
+                PathBuf::from(&self.test_img),
+                PathBuf::from(&self.test_label),
+            )),
+            _ => bail!("[error]::fileutils: unrecognized batch name {:?}", batch),
+        }
+    }
+
     pub fn create_folders(&self) -> Result<(), Error> {
         fs::create_dir_all(&self.train_img.to_owned())?;
         fs::create_dir_all(&self.train_label.to_owned())?;
@@ -82,32 +155,206 @@ impl ExportFolderOptions {
     }
 }
 
-/// get all images in a folder
-pub fn get_all_images(input_folder: &str) -> Vec<PathBuf> {
-    read_dir(input_folder)
-        .unwrap()
-        .filter_map(|f| f.ok())
-        .filter(|f| match f.path().extension() {
-            None => false,
-            Some(ex) => ex == "jpeg" || ex == "jpg" || ex == "png",
-        })
-        .map(|f| f.path())
-        .collect()
+/// how source images end up in an export folder,
+/// everything but `move` leaves the source folder untouched
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum ExportMode {
+    #[default]
+    Copy,
+    Hardlink,
+    Symlink,
+    /// the old behaviour, moves the source files
+    Move,
 }
 
-pub fn get_all_txts(input: &str) -> Result<Vec<PathBuf>, Error> {
-    let all_jsons: Vec<PathBuf> = fs::read_dir(&input)
-        .unwrap()
-        .filter_map(|f| f.ok())
-        .filter(|f| match f.path().extension() {
-            None => false,
-            Some(ex) => ex == "txt",
-        })
-        .map(|f| f.path())
+/// puts `source` at `destination` according to `mode`,
+/// an existing destination is replaced, exporting a file onto itself does nothing
+pub fn export_file(source: &Path, destination: &Path, mode: ExportMode) -> Result<(), Error> {
+    if let (Ok(src), Ok(dst)) = (fs::canonicalize(source), fs::canonicalize(destination)) {
+        if src == dst {
+            return Ok(());
+        }
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if destination.symlink_metadata().is_ok() {
+        fs::remove_file(destination)?;
+    }
+    match mode {
+        ExportMode::Copy => {
+            fs::copy(source, destination)?;
+        }
+        ExportMode::Hardlink => fs::hard_link(source, destination)?,
+        ExportMode::Symlink => symlink_file(&fs::canonicalize(source)?, destination)?,
+        ExportMode::Move => fs::rename(source, destination)?,
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn symlink_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(source, destination)
+}
+
+/// image extensions we look for, matched case-insensitively
+pub const IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "bmp", "webp", "tiff", "tif"];
+
+/// what happens to symlinks while searching a folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum SymlinkPolicy {
+    /// symlinked files and folders are searched like any other
+    #[default]
+    Follow,
+    Skip,
+}
+
+// which files `find_files` returns, also used as cli flags (a plain comment,
+// clap would take a doc comment as the description of every binary flattening it)
+#[derive(Args, Debug, Clone, Default)]
+pub struct DiscoveryOptions {
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// only look in the folder itself, not in its subfolders
+    pub flat: bool,
+
+    #[arg(long)]
+    /// only files matching one of these globs, relative
+    /// to the folder (e.g. `session_*/**`), can be repeated
+    pub include: Vec<String>,
+
+    #[arg(long)]
+    /// skip files matching one of these globs, can be repeated
+    pub exclude: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = SymlinkPolicy::Follow)]
+    pub symlinks: SymlinkPolicy,
+
+    #[arg(skip)]
+    /// folders that are never searched, e.g. an export folder inside the source
+    pub skip_folders: Vec<PathBuf>,
+}
+
+fn build_globset(patterns: &[String]) -> Result<Option<GlobSet>, Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter() {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// case-insensitive extension check
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    match path.extension() {
+        Some(ext) => {
+            let ext = ext.to_string_lossy().to_lowercase();
+            extensions.iter().any(|e| *e == ext)
+        }
+        None => false,
+    }
+}
+
+/// every file under `input` with one of the extensions, sorted by path.
+/// include/exclude globs are matched against the path relative to `input`
+pub fn find_files(
+    input: &Path,
+    extensions: &[&str],
+    options: &DiscoveryOptions,
+) -> Result<Vec<PathBuf>, Error> {
+    let include = build_globset(&options.include)?;
+    let exclude = build_globset(&options.exclude)?;
+    let skip_folders: Vec<PathBuf> = options
+        .skip_folders
+        .iter()
+        .filter_map(|folder| fs::canonicalize(folder).ok())
         .collect();
-    Ok(all_jsons)
+    let mut walker = WalkDir::new(input)
+        .follow_links(options.symlinks == SymlinkPolicy::Follow)
+        .sort_by_file_name();
+    if options.flat {
+        walker = walker.max_depth(1);
+    }
+    let walker = walker.into_iter().filter_entry(|entry| {
+        if entry.depth() == 0 {
+            return true;
+        }
+        if options.symlinks == SymlinkPolicy::Skip && entry.path_is_symlink() {
+            return false;
+        }
+        if skip_folders.is_empty() || !entry.file_type().is_dir() {
+            return true;
+        }
+        match fs::canonicalize(entry.path()) {
+            Ok(folder) => !skip_folders.contains(&folder),
+            Err(_) => true,
+        }
+    });
+
+    let mut all_files: Vec<PathBuf> = vec![];
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.loop_ancestor().is_some() => {
+                println!("[info]::fileutils: skipping symlink loop at {:?}", e.path());
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let path = entry.path();
+        if !entry.file_type().is_file() || !has_extension(path, extensions) {
+            continue;
+        }
+        let relative = path.strip_prefix(input).unwrap_or(path);
+        if matches!(&include, Some(include) if !include.is_match(relative)) {
+            continue;
+        }
+        if matches!(&exclude, Some(exclude) if exclude.is_match(relative)) {
+            continue;
+        }
+        all_files.push(path.to_path_buf());
+    }
+    Ok(all_files)
 }
 
+/// `path` relative to `root`, only the file name
+/// if it isn't inside `root` (e.g. `../image.png`)
+pub fn relative_path(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => PathBuf::from(path.file_name().unwrap_or_default()),
+    }
+}
+
+/// an image with the same name as `file` (any case of the
+/// known image extensions), for labels that don't know their image
+pub fn find_image_for(file: &Path) -> Option<PathBuf> {
+    IMAGE_EXTENSIONS
+        .iter()
+        .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+        .map(|ext| file.with_extension(ext))
+        .find(|image| image.is_file())
+}
+
+/// get all images in a folder and its subfolders
+pub fn get_all_images(input_folder: &str) -> Vec<PathBuf> {
+    find_files(
+        Path::new(input_folder),
+        &IMAGE_EXTENSIONS,
+        &DiscoveryOptions::default(),
+    )
+    .unwrap()
+}
+
+pub fn get_all_txts(input: &str) -> Result<Vec<PathBuf>, Error> {
+    find_files(Path::new(input), &["txt"], &DiscoveryOptions::default())
+}
 
 pub fn get_json_from_image(input: &PathBuf) -> Result<PathBuf, Error> {
     let mut _json_file = input.to_owned();
@@ -116,17 +363,116 @@ pub fn get_json_from_image(input: &PathBuf) -> Result<PathBuf, Error> {
 }
 
 
+/// yolo label of an image, `<split>/images/x.jpg` maps to
+/// `<split>/labels/x.txt`, anything else looks next to the image
+pub fn get_txt_from_image(input: &Path) -> PathBuf {
+    let mut _txt_file = match input.parent() {
+        Some(parent) if parent.file_name().is_some_and(|p| p == "images") => parent
+            .with_file_name("labels")
+            .join(input.file_name().unwrap()),
+        _ => input.to_path_buf(),
+    };
+    _txt_file.set_extension("txt");
+    _txt_file
+}
+
+/// reads every line of a yolo txt, empty lines are skipped
+pub fn read_yolo_from_txt(input: &Path) -> Result<Vec<YoloLabel>, Error> {
+    let txt = fs::read_to_string(input)?;
+    txt.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(YoloLabel::from_line)
+        .collect()
+}
+
+pub fn read_data_yaml(input: &Path) -> Result<DatasetInfo, Error> {
+    let yaml_file = fs::File::open(input)?;
+    let data_yaml: DatasetInfo = serde_yaml::from_reader(yaml_file)?;
+    Ok(data_yaml)
+}
+
+/// what to do with labels that aren't in a fixed class list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum UnknownClassPolicy {
+    /// stop before anything is written
+    #[default]
+    Error,
+    /// print them and leave their shapes out
+    Warn,
+    /// add them after the known classes
+    Append,
+}
+
+/// class names from a `data.yaml`, or from a plain list
+/// with one name per line (`classes.txt`, `obj.names`)
+pub fn read_class_names(input: &Path) -> Result<Vec<String>, Error> {
+    if has_extension(input, &["yaml", "yml"]) {
+        return Ok(read_data_yaml(input)?.names);
+    }
+    let names_str = match fs::read_to_string(input) {
+        Ok(names_str) => names_str,
+        Err(e) => bail!("[error]::fileutils: cannot read {:?}: {}", input, e),
+    };
+    Ok(names_str
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// the class list to export with, `fixed` keeps its indices
+/// and `found` labels missing from it are handled by `policy`
+pub fn resolve_classes(
+    fixed: &[String],
+    found: &[String],
+    policy: UnknownClassPolicy,
+) -> Result<Vec<String>, Error> {
+    // first appearance order, `found` doesn't have to be sorted
+    let mut unknown: Vec<String> = vec![];
+    for label in found.iter() {
+        if !fixed.contains(label) && !unknown.contains(label) {
+            unknown.push(label.to_owned());
+        }
+    }
+    let mut classes = fixed.to_vec();
+    if unknown.is_empty() {
+        return Ok(classes);
+    }
+    match policy {
+        UnknownClassPolicy::Error => bail!(
+            "[error]::fileutils: {:?} are not in the class list, use `--unknown-classes warn` to skip them or `append` to add them",
+            unknown
+        ),
+        UnknownClassPolicy::Warn => println!(
+            "[warning]::fileutils: {:?} are not in the class list, their shapes are left out",
+            unknown
+        ),
+        UnknownClassPolicy::Append => {
+            println!(
+                "[info]::fileutils: appending {:?} to the class list",
+                unknown
+            );
+            classes.extend(unknown);
+        }
+    }
+    Ok(classes)
+}
+
+/// sha256 of a file's contents as hex, identical files have the same hash
+pub fn content_hash(input: &Path) -> Result<String, Error> {
+    let mut file = fs::File::open(input)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 pub fn get_all_jsons(input: &str) -> Result<Vec<PathBuf>, Error> {
-    let all_jsons: Vec<PathBuf> = fs::read_dir(&input)
-        .unwrap()
-        .filter_map(|f| f.ok())
-        .filter(|f| match f.path().extension() {
-            None => false,
-            Some(ex) => ex == "json",
-        })
-        .map(|f| f.path())
-        .collect();
-    Ok(all_jsons)
+    find_files(Path::new(input), &["json"], &DiscoveryOptions::default())
+}
+
+/// pascal voc annotations
+pub fn get_all_xmls(input: &str) -> Result<Vec<PathBuf>, Error> {
+    find_files(Path::new(input), &["xml"], &DiscoveryOptions::default())
 }
 
 /// takes a all classes label list and then puts it in a mf HASHMAP AHHHHHHHHHHHHHHHHHH
@@ -182,6 +528,26 @@ pub fn write_yolo_to_txt(
     Ok(())
 }
 
+/// same as `write_yolo_to_txt` but for segmentation labels,
+/// one `class x1 y1 x2 y2 ...` line per polygon
+pub fn write_yolo_seg_to_txt(
+    input_seg: Vec<YoloSegAnnotation>,
+    image_path: &PathBuf,
+) -> Result<(), Error> {
+    let mut _txt_file_name = image_path.to_owned();
+    _txt_file_name.set_extension("txt");
+    let mut txtfile = fs::File::create(&_txt_file_name)?;
+    for shape in input_seg.iter() {
+        let mut line = format!("{:?}", &shape.class);
+        for point in shape.points.iter() {
+            line.push_str(&format!(" {:?} {:?}", &point[0], &point[1]));
+        }
+        line.push('\n');
+        txtfile.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
 /// get config from filename
 pub fn get_config_from_name(
     config_path: &Option<&str>,
@@ -206,13 +572,110 @@ pub fn write_data_yaml(
     all_classes: &Vec<String>,
 ) -> Result<(), Error> {
     let data_yaml: DatasetInfo = DatasetInfo::new(export_options, all_classes)?;
-    let yaml_fname = export_options.export_folder.to_owned().join("data.yaml");
-    let mut yaml_file = fs::File::create(yaml_fname).unwrap();
-    serde_yaml::to_writer(&mut yaml_file, &data_yaml)?;
+    write_dataset_info(&data_yaml, &export_options.export_folder)?;
     // println!("YAML DIR: {:?}", export_options.export_folder.to_owned().join("data.yaml"));
     Ok(())
 }
 
+/// how an export describes itself to the trainer
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum DataFormat {
+    /// `data.yaml` for ultralytics (yolov5 and later)
+    #[default]
+    Ultralytics,
+    /// images and txts side by side per batch, with `obj.data`,
+    /// `obj.names` and `train.txt`/`val.txt`/`test.txt` image lists
+    Darknet,
+}
+
+/// how paths are written into the darknet list files and `obj.data`
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum ListPaths {
+    #[default]
+    Absolute,
+    /// relative to the export folder, behind `--list-prefix` if given
+    Relative,
+}
+
+// darknet export flags, only used with `--data-format darknet`
+#[derive(Args, Debug, Clone)]
+pub struct DarknetOptions {
+    #[arg(long, value_enum, default_value_t = ListPaths::Absolute)]
+    pub list_paths: ListPaths,
+
+    #[arg(long)]
+    /// put in front of relative paths, e.g. `data/obj` if
+    /// the export is moved there and darknet runs from its root
+    pub list_prefix: Option<String>,
+
+    #[arg(long, default_value = "backup/")]
+    /// where darknet saves the weights while training
+    pub backup: String,
+}
+
+/// writes the `data.yaml` or the darknet files of an export,
+/// after the images are exported since darknet lists every one of them
+pub fn write_dataset_description(
+    export_options: &ExportFolderOptions,
+    all_classes: &Vec<String>,
+    format: DataFormat,
+    darknet_options: &DarknetOptions,
+) -> Result<(), Error> {
+    match format {
+        DataFormat::Ultralytics => write_data_yaml(export_options, all_classes),
+        DataFormat::Darknet => write_darknet_data(export_options, all_classes, darknet_options),
+    }
+}
+
+/// `obj.names`, one image list per batch and an `obj.data` pointing at them.
+/// darknet looks for the txt next to the image (or swaps `images` for `labels`)
+pub fn write_darknet_data(
+    export_options: &ExportFolderOptions,
+    all_classes: &[String],
+    options: &DarknetOptions,
+) -> Result<(), Error> {
+    let folder = &export_options.export_folder;
+    let list_path = |path: &Path| -> Result<String, Error> {
+        let path = std::path::absolute(path)?;
+        let path = match options.list_paths {
+            ListPaths::Absolute => path,
+            ListPaths::Relative => {
+                let prefix = Path::new(options.list_prefix.as_deref().unwrap_or(""));
+                prefix.join(relative_path(folder, &path))
+            }
+        };
+        Ok(path.to_string_lossy().to_string())
+    };
+    let names: String = all_classes.iter().map(|c| format!("{}\n", c)).collect();
+    fs::write(folder.join("obj.names"), names)?;
+    for batch in ["train", "val", "test"] {
+        let (img_folder, _) = export_options.batch_folders(batch)?;
+        let mut image_list = String::new();
+        for image in find_files(&img_folder, &IMAGE_EXTENSIONS, &DiscoveryOptions::default())? {
+            image_list.push_str(&list_path(&image)?);
+            image_list.push('\n');
+        }
+        fs::write(folder.join(format!("{}.txt", batch)), image_list)?;
+    }
+    let obj_data = format!(
+        "classes = {}\ntrain = {}\nvalid = {}\nnames = {}\nbackup = {}\n",
+        all_classes.len(),
+        list_path(&folder.join("train.txt"))?,
+        list_path(&folder.join("val.txt"))?,
+        list_path(&folder.join("obj.names"))?,
+        options.backup
+    );
+    fs::write(folder.join("obj.data"), obj_data)?;
+    Ok(())
+}
+
+/// writes `data.yaml` into a folder
+pub fn write_dataset_info(data_yaml: &DatasetInfo, folder: &Path) -> Result<(), Error> {
+    let mut yaml_file = fs::File::create(folder.join("data.yaml"))?;
+    serde_yaml::to_writer(&mut yaml_file, data_yaml)?;
+    Ok(())
+}
+
 pub fn write_labelme_to_json(
     input_labelme: &LabelmeAnnotation,
     image_path: &PathBuf,
@@ -230,3 +693,167 @@ pub fn open_image(input_path: &PathBuf) -> Result<DynamicImage, Error> {
     let img = image::open(input_path)?;
     Ok(img)
 }
+
+#[cfg(test)]
+mod test_fileutils {
+    use crate::fileutils::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn export_modes_keep_source() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&_tmp).unwrap();
+        let _source = _tmp.join("test.png");
+        fs::copy("test/test.png", &_source).unwrap();
+        for (_mode, _name) in [
+            (ExportMode::Copy, "copy"),
+            (ExportMode::Hardlink, "hardlink"),
+            (ExportMode::Symlink, "symlink"),
+        ] {
+            let _dest = _tmp.join(_name).join("test.png");
+            export_file(&_source, &_dest, _mode).unwrap();
+            // twice, the second export replaces the first
+            export_file(&_source, &_dest, _mode).unwrap();
+            assert!(_source.exists());
+            assert_eq!(fs::read(&_dest).unwrap(), fs::read(&_source).unwrap());
+        }
+        assert!(_tmp.join("symlink/test.png").symlink_metadata().unwrap().is_symlink());
+        export_file(&_source, &_source, ExportMode::Copy).unwrap();
+        assert!(_source.exists());
+
+        let _moved = _tmp.join("move/test.png");
+        export_file(&_source, &_moved, ExportMode::Move).unwrap();
+        assert!(!_source.exists() && _moved.exists());
+        fs::remove_dir_all(&_tmp).unwrap();
+    }
+
+    #[test]
+    fn find_files_recursive() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        for _file in [
+            "a.png",
+            "session1/b.JPG",
+            "session1/deep/c.webp",
+            "session2/d.tiff",
+            "session2/notes.txt",
+            "export/e.png",
+        ] {
+            let _path = _tmp.join(_file);
+            fs::create_dir_all(_path.parent().unwrap()).unwrap();
+            fs::write(&_path, b"").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(_tmp.join("session1"), _tmp.join("linked")).unwrap();
+        let _found = |options: &DiscoveryOptions| -> Vec<String> {
+            find_files(&_tmp, &IMAGE_EXTENSIONS, options)
+                .unwrap()
+                .iter()
+                .map(|p| relative_path(&_tmp, p).to_string_lossy().to_string())
+                .collect()
+        };
+
+        let mut _options = DiscoveryOptions {
+            symlinks: SymlinkPolicy::Skip,
+            skip_folders: vec![_tmp.join("export")],
+            ..Default::default()
+        };
+        assert_eq!(
+            _found(&_options),
+            vec![
+                "a.png",
+                "session1/b.JPG",
+                "session1/deep/c.webp",
+                "session2/d.tiff"
+            ]
+        );
+        _options.symlinks = SymlinkPolicy::Follow;
+        #[cfg(unix)]
+        assert_eq!(_found(&_options).len(), 6);
+        _options.include = vec![String::from("session*/**")];
+        _options.exclude = vec![String::from("**/deep/**")];
+        assert_eq!(_found(&_options), vec!["session1/b.JPG", "session2/d.tiff"]);
+        let _flat = DiscoveryOptions {
+            flat: true,
+            ..Default::default()
+        };
+        assert_eq!(_found(&_flat), vec!["a.png"]);
+
+        assert_eq!(
+            find_image_for(&_tmp.join("session1/b.txt")),
+            Some(_tmp.join("session1/b.JPG"))
+        );
+        fs::remove_dir_all(&_tmp).unwrap();
+    }
+
+    #[test]
+    fn fixed_class_list() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&_tmp).unwrap();
+        fs::write(_tmp.join("classes.txt"), "mouth\neye\n\near\n").unwrap();
+        let _names = read_class_names(&_tmp.join("classes.txt")).unwrap();
+        assert_eq!(_names, vec!["mouth", "eye", "ear"]);
+        assert_eq!(read_class_names(Path::new("test/test.yaml")).unwrap()[0], "10C");
+
+        // unsorted, with a repeated unknown label
+        let _found: Vec<String> = ["nose", "eye", "lip", "nose"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        assert!(resolve_classes(&_names, &_found, UnknownClassPolicy::Error).is_err());
+        assert_eq!(
+            resolve_classes(&_names, &_found, UnknownClassPolicy::Warn).unwrap(),
+            _names
+        );
+        assert_eq!(
+            resolve_classes(&_names, &_found, UnknownClassPolicy::Append).unwrap(),
+            vec!["mouth", "eye", "ear", "nose", "lip"]
+        );
+        fs::remove_dir_all(_tmp).unwrap();
+    }
+
+    #[test]
+    fn data_yaml_names_layouts() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&_tmp).unwrap();
+        let _options = ExportFolderOptions::new(_tmp.to_str().unwrap()).unwrap();
+        let _classes = vec![String::from("ear"), String::from("eye")];
+        write_data_yaml(&_options, &_classes).unwrap();
+        let _yaml = fs::read_to_string(_tmp.join("data.yaml")).unwrap();
+        assert!(_yaml.contains("0: ear") && _yaml.contains("val: val/images"));
+        let _data = read_data_yaml(&_tmp.join("data.yaml")).unwrap();
+        assert_eq!(_data.names, _classes);
+        assert_eq!(_data.path, Some(_tmp.to_string_lossy().to_string()));
+        // the older list layout without `path`
+        assert_eq!(read_data_yaml(Path::new("test/test.yaml")).unwrap().path, None);
+        fs::write(_tmp.join("gap.yaml"), "names:\n  0: ear\n  2: eye\n").unwrap();
+        assert!(read_data_yaml(&_tmp.join("gap.yaml")).is_err());
+
+        let _darknet = ExportFolderOptions::darknet(_tmp.to_str().unwrap()).unwrap();
+        _darknet.create_folders().unwrap();
+        fs::copy("test/test.png", _tmp.join("train/test.png")).unwrap();
+        let mut _darknet_options = DarknetOptions {
+            list_paths: ListPaths::Absolute,
+            list_prefix: None,
+            backup: String::from("backup/"),
+        };
+        write_darknet_data(&_darknet, &_classes, &_darknet_options).unwrap();
+        assert_eq!(
+            fs::read_to_string(_tmp.join("train.txt")).unwrap(),
+            format!("{}\n", _tmp.join("train/test.png").to_string_lossy())
+        );
+        _darknet_options.list_paths = ListPaths::Relative;
+        _darknet_options.list_prefix = Some(String::from("data/obj"));
+        write_darknet_data(&_darknet, &_classes, &_darknet_options).unwrap();
+        assert_eq!(
+            fs::read_to_string(_tmp.join("train.txt")).unwrap(),
+            "data/obj/train/test.png\n"
+        );
+        let _obj_data = fs::read_to_string(_tmp.join("obj.data")).unwrap();
+        assert!(_obj_data.starts_with("classes = 2\ntrain = data/obj/train.txt\n"));
+        assert_eq!(
+            fs::read_to_string(_tmp.join("obj.names")).unwrap(),
+            "ear\neye\n"
+        );
+        fs::remove_dir_all(_tmp).unwrap();
+    }
+}
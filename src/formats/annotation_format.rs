@@ -0,0 +1,113 @@
+use crate::dataset::Dataset;
+use anyhow::{bail, Error, Result};
+use std::path::Path;
+
+use super::coco_format::CocoFormat;
+use super::labelme_format::LabelmeFormat;
+use super::voc_format::VocFormat;
+use super::yolo_format::YoloFormat;
+
+/// names accepted by `get_format`
+pub const FORMAT_NAMES: [&str; 5] = ["labelme", "yolo", "yolo-seg", "coco", "voc"];
+
+/// reads a whole dataset folder into a `Dataset`
+/// and writes one back out, images are copied
+/// into the output folder along with the labels
+pub trait AnnotationFormat: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// true if `input` looks like a dataset of this format
+    fn detect(&self, input: &Path) -> bool;
+    fn read_dataset(&self, input: &Path) -> Result<Dataset, Error>;
+    fn write_dataset(&self, dataset: &Dataset, output: &Path) -> Result<(), Error>;
+}
+
+/// looks up a format by name
+pub fn get_format(name: &str) -> Result<Box<dyn AnnotationFormat>, Error> {
+    let format: Box<dyn AnnotationFormat> = match name {
+        "labelme" => Box::new(LabelmeFormat::default()),
+        "yolo" => Box::new(YoloFormat {
+            segment: false,
+            data_yaml: None,
+        }),
+        "yolo-seg" => Box::new(YoloFormat {
+            segment: true,
+            data_yaml: None,
+        }),
+        "coco" => Box::new(CocoFormat),
+        "voc" => Box::new(VocFormat),
+        _ => bail!(
+            "[error]::formats: unknown format {:?}, supported formats: {:?}",
+            name,
+            FORMAT_NAMES
+        ),
+    };
+    Ok(format)
+}
+
+/// guesses the format of a dataset folder (or coco file),
+/// yolo wins if there is a data.yaml, then voc xmls,
+/// then coco jsons and finally labelme jsons
+pub fn detect_format(input: &Path) -> Result<&'static str, Error> {
+    for name in ["yolo-seg", "yolo", "voc", "coco", "labelme"] {
+        if get_format(name)?.detect(input) {
+            return Ok(name);
+        }
+    }
+    bail!(
+        "[error]::formats: cannot detect the annotation format of {:?}",
+        input
+    )
+}
+
+/// reads with one format and writes with another
+pub fn convert_dataset(
+    input: &Path,
+    input_format: &str,
+    output: &Path,
+    output_format: &str,
+) -> Result<Dataset, Error> {
+    let dataset = get_format(input_format)?.read_dataset(input)?;
+    get_format(output_format)?.write_dataset(&dataset, output)?;
+    Ok(dataset)
+}
+
+#[cfg(test)]
+mod test_annotation_format {
+    use crate::formats::annotation_format::*;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    #[test]
+    fn convert_every_format() {
+        let _source = get_format("labelme")
+            .unwrap()
+            .read_dataset(Path::new("test"))
+            .unwrap();
+        let _source_item = _source
+            .items
+            .iter()
+            .find(|item| item.image_path == PathBuf::from("test.png"))
+            .unwrap();
+        for name in FORMAT_NAMES {
+            let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+            let _written = convert_dataset(Path::new("test"), "labelme", &_tmp, name).unwrap();
+            assert_eq!(detect_format(&_tmp).unwrap(), name);
+            let _read = get_format(name).unwrap().read_dataset(&_tmp).unwrap();
+            assert_eq!(_read.items.len(), _written.items.len(), "{}", name);
+            let _item = _read
+                .items
+                .iter()
+                .find(|item| item.image_path.ends_with("test.png"))
+                .unwrap();
+            assert_eq!((_item.width, _item.height), (1024, 1024), "{}", name);
+            assert_eq!(_item.shapes.len(), _source_item.shapes.len(), "{}", name);
+            let _bbox = _item.shapes[0].bbox().unwrap();
+            let _source_bbox = _source_item.shapes[0].bbox().unwrap();
+            assert!((_bbox.x1 - _source_bbox.x1).abs() < 0.1, "{}", name);
+            assert!((_bbox.y2 - _source_bbox.y2).abs() < 0.1, "{}", name);
+            std::fs::remove_dir_all(&_tmp).unwrap();
+        }
+        assert!(get_format("darknet").is_err());
+        assert_eq!(detect_format(Path::new("test")).unwrap(), "labelme");
+    }
+}
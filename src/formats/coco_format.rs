@@ -0,0 +1,84 @@
+use crate::coco::{read_coco_from_file, write_coco_to_json, CocoDataset};
+use crate::dataset::{copy_image, Dataset, DatasetItem};
+use crate::fileutils::get_all_jsons;
+use crate::label::LabelmeAnnotation;
+use anyhow::{bail, Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::annotation_format::AnnotationFormat;
+
+/// name of the coco file written by `CocoFormat`
+pub const COCO_FILE_NAME: &str = "instances.json";
+
+/// a single coco json, `file_name`s are
+/// relative to the folder the json is in
+#[derive(Debug, Clone)]
+pub struct CocoFormat;
+
+/// input can be the coco json itself or a folder with
+/// exactly one `instances*.json` (or one json at all)
+pub fn find_coco_file(input: &Path) -> Result<PathBuf, Error> {
+    if input.is_file() {
+        return Ok(input.to_path_buf());
+    }
+    let all_json = get_all_jsons(input.to_str().unwrap())?;
+    let instances: Vec<&PathBuf> = all_json
+        .iter()
+        .filter(|json| {
+            json.file_name()
+                .is_some_and(|f| f.to_string_lossy().starts_with("instances"))
+        })
+        .collect();
+    match (instances.len(), all_json.len()) {
+        (1, _) => Ok(instances[0].to_owned()),
+        (0, 1) => Ok(all_json[0].to_owned()),
+        _ => bail!(
+            "[error]::coco_format: cannot tell which json in {:?} is the coco file",
+            input
+        ),
+    }
+}
+
+impl AnnotationFormat for CocoFormat {
+    fn name(&self) -> &'static str {
+        "coco"
+    }
+
+    fn detect(&self, input: &Path) -> bool {
+        match find_coco_file(input) {
+            Ok(coco_file) => read_coco_from_file(&coco_file).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn read_dataset(&self, input: &Path) -> Result<Dataset, Error> {
+        let coco_file = find_coco_file(input)?;
+        let coco = read_coco_from_file(&coco_file)?;
+        let mut dataset = Dataset::new(coco_file.parent().unwrap());
+        let mut categories = coco.categories.to_owned();
+        categories.sort_by_key(|c| c.id);
+        dataset.classes = categories.into_iter().map(|c| c.name).collect();
+        for labelme in coco.to_labelme()?.iter() {
+            dataset.items.push(DatasetItem::from_labelme(
+                Path::new(&labelme.imagePath),
+                labelme,
+            ));
+        }
+        Ok(dataset)
+    }
+
+    fn write_dataset(&self, dataset: &Dataset, output: &Path) -> Result<(), Error> {
+        fs::create_dir_all(output)?;
+        let mut all_labelme: Vec<LabelmeAnnotation> = vec![];
+        for item in dataset.items.iter() {
+            copy_image(&dataset.image_source(item), &output.join(&item.image_path))?;
+            let mut labelme = item.to_labelme();
+            labelme.imagePath = item.image_path.to_string_lossy().to_string();
+            all_labelme.push(labelme);
+        }
+        let coco = CocoDataset::from_annotations(&all_labelme, &dataset.all_classes())?;
+        write_coco_to_json(&coco, &output.join(COCO_FILE_NAME))?;
+        Ok(())
+    }
+}
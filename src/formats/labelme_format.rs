@@ -0,0 +1,65 @@
+use crate::dataset::{copy_image, Dataset, DatasetItem};
+use crate::fileutils::{get_all_jsons, open_image, write_labelme_to_json};
+use crate::image_utils::dynimg2string;
+use crate::label::read_labels_from_file;
+use anyhow::{Error, Result};
+use image::DynamicImage;
+use rayon::prelude::*;
+use std::path::Path;
+
+use super::annotation_format::AnnotationFormat;
+
+/// labelme jsons next to their images
+#[derive(Debug, Clone, Default)]
+pub struct LabelmeFormat {
+    /// embed the image into the jsons (`imageData`)
+    pub image_data: bool,
+}
+
+impl AnnotationFormat for LabelmeFormat {
+    fn name(&self) -> &'static str {
+        "labelme"
+    }
+
+    fn detect(&self, input: &Path) -> bool {
+        match get_all_jsons(input.to_str().unwrap()) {
+            Ok(all_json) => all_json
+                .iter()
+                .any(|json| read_labels_from_file(json.to_str().unwrap()).is_ok()),
+            Err(_) => false,
+        }
+    }
+
+    fn read_dataset(&self, input: &Path) -> Result<Dataset, Error> {
+        let mut dataset = Dataset::new(input);
+        let mut all_json = get_all_jsons(input.to_str().unwrap())?;
+        all_json.sort();
+        for json in all_json.iter() {
+            let labelme = read_labels_from_file(json.to_str().unwrap())?;
+            let image_path = json.with_file_name(&labelme.imagePath);
+            let relative_path = image_path.strip_prefix(input).unwrap_or(&image_path);
+            dataset
+                .items
+                .push(DatasetItem::from_labelme(relative_path, &labelme));
+        }
+        dataset.collect_classes();
+        Ok(dataset)
+    }
+
+    fn write_dataset(&self, dataset: &Dataset, output: &Path) -> Result<(), Error> {
+        dataset
+            .items
+            .par_iter()
+            .try_for_each(|item| -> Result<(), Error> {
+                let dest_image = output.join(&item.image_path);
+                copy_image(&dataset.image_source(item), &dest_image)?;
+                let mut labelme = item.to_labelme();
+                if self.image_data {
+                    // jpeg can't store alpha
+                    let rgb_img = DynamicImage::ImageRgb8(open_image(&dest_image)?.to_rgb8());
+                    labelme.imageData = Some(dynimg2string(&rgb_img)?);
+                }
+                write_labelme_to_json(&labelme, &dest_image)
+            })
+    }
+}
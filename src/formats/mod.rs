@@ -0,0 +1,5 @@
+pub mod annotation_format;
+pub mod coco_format;
+pub mod labelme_format;
+pub mod voc_format;
+pub mod yolo_format;
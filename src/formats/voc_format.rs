@@ -0,0 +1,50 @@
+use crate::dataset::{copy_image, Dataset, DatasetItem};
+use crate::fileutils::get_all_xmls;
+use crate::voc::{read_voc_from_file, write_voc_to_xml, VocAnnotation};
+use anyhow::{Error, Result};
+use std::path::Path;
+
+use super::annotation_format::AnnotationFormat;
+
+/// pascal voc xmls next to their images
+#[derive(Debug, Clone)]
+pub struct VocFormat;
+
+impl AnnotationFormat for VocFormat {
+    fn name(&self) -> &'static str {
+        "voc"
+    }
+
+    fn detect(&self, input: &Path) -> bool {
+        match get_all_xmls(input.to_str().unwrap()) {
+            Ok(all_xml) => all_xml.iter().any(|xml| read_voc_from_file(xml).is_ok()),
+            Err(_) => false,
+        }
+    }
+
+    fn read_dataset(&self, input: &Path) -> Result<Dataset, Error> {
+        let mut dataset = Dataset::new(input);
+        let mut all_xml = get_all_xmls(input.to_str().unwrap())?;
+        all_xml.sort();
+        for xml in all_xml.iter() {
+            let labelme = read_voc_from_file(xml)?.to_labelme();
+            let image_path = xml.with_file_name(&labelme.imagePath);
+            let relative_path = image_path.strip_prefix(input).unwrap_or(&image_path);
+            dataset
+                .items
+                .push(DatasetItem::from_labelme(relative_path, &labelme));
+        }
+        dataset.collect_classes();
+        Ok(dataset)
+    }
+
+    fn write_dataset(&self, dataset: &Dataset, output: &Path) -> Result<(), Error> {
+        for item in dataset.items.iter() {
+            let dest_image = output.join(&item.image_path);
+            copy_image(&dataset.image_source(item), &dest_image)?;
+            let voc = VocAnnotation::from_labelme(&item.to_labelme())?;
+            write_voc_to_xml(&voc, &dest_image)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use crate::dataset::{copy_image, Dataset, DatasetItem};
+use crate::fileutils::{
+    get_all_images, get_txt_from_image, read_data_yaml, read_yolo_from_txt, write_dataset_info,
+    write_yolo_seg_to_txt, write_yolo_to_txt, DatasetInfo,
+};
+use crate::label::{Shape, YoloLabel};
+use anyhow::{bail, Error, Result};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::annotation_format::AnnotationFormat;
+
+/// split folders looked for in a yolo export tree
+pub const SPLIT_NAMES: [&str; 3] = ["train", "val", "test"];
+
+/// yolo txts with a `data.yaml` for the class names,
+/// either `<split>/images` + `<split>/labels` per split,
+/// `images` + `labels`, or everything in one folder
+#[derive(Debug, Clone)]
+pub struct YoloFormat {
+    /// write polygons (`yolo-seg`) instead of boxes
+    pub segment: bool,
+    /// class names to read with, defaults to `<input>/data.yaml`
+    pub data_yaml: Option<PathBuf>,
+}
+
+/// every image folder of a yolo tree, with the split it belongs to
+pub fn find_yolo_image_folders(input: &Path) -> Vec<(PathBuf, Option<String>)> {
+    let split_folders: Vec<(PathBuf, Option<String>)> = SPLIT_NAMES
+        .iter()
+        .map(|split| (input.join(split).join("images"), Some(String::from(*split))))
+        .filter(|(folder, _)| folder.is_dir())
+        .collect();
+    if !split_folders.is_empty() {
+        return split_folders;
+    }
+    match input.join("images").is_dir() {
+        true => vec![(input.join("images"), None)],
+        false => vec![(input.to_path_buf(), None)],
+    }
+}
+
+impl AnnotationFormat for YoloFormat {
+    fn name(&self) -> &'static str {
+        match self.segment {
+            true => "yolo-seg",
+            false => "yolo",
+        }
+    }
+
+    /// a data.yaml is required, segmentation datasets
+    /// are told apart by their first label line
+    fn detect(&self, input: &Path) -> bool {
+        if !input.is_dir() || !input.join("data.yaml").exists() {
+            return false;
+        }
+        let first_label = find_yolo_image_folders(input)
+            .iter()
+            .flat_map(|(folder, _)| get_all_images(folder.to_str().unwrap()))
+            .map(|img| get_txt_from_image(&img))
+            .filter_map(|txt| read_yolo_from_txt(&txt).ok())
+            .find_map(|labels| labels.into_iter().next());
+        match first_label {
+            Some(YoloLabel::Segment(_)) => self.segment,
+            _ => !self.segment,
+        }
+    }
+
+    fn read_dataset(&self, input: &Path) -> Result<Dataset, Error> {
+        let data_yaml_path = match &self.data_yaml {
+            Some(data_yaml) => data_yaml.to_owned(),
+            None => input.join("data.yaml"),
+        };
+        if !data_yaml_path.exists() {
+            bail!(
+                "[error]::yolo_format: {:?} needs a data.yaml with the class names",
+                input
+            );
+        }
+        let mut dataset = Dataset::new(input);
+        dataset.classes = read_data_yaml(&data_yaml_path)?.names;
+        for (folder, split) in find_yolo_image_folders(input) {
+            let mut all_imgs = get_all_images(folder.to_str().unwrap());
+            all_imgs.sort();
+            let items = all_imgs
+                .par_iter()
+                .map(|img| -> Result<DatasetItem, Error> {
+                    let dims = image::image_dimensions(img)?;
+                    let txt = get_txt_from_image(img);
+                    let shapes: Vec<Shape> = match txt.exists() {
+                        true => read_yolo_from_txt(&txt)?
+                            .iter()
+                            .map(|yolo| yolo.to_shape(&dataset.classes, &dims))
+                            .collect::<Result<Vec<Shape>, Error>>()?,
+                        false => vec![],
+                    };
+                    Ok(DatasetItem {
+                        image_path: img.strip_prefix(input).unwrap_or(img).to_path_buf(),
+                        width: dims.0,
+                        height: dims.1,
+                        shapes,
+                        flags: HashMap::new(),
+                        split: split.to_owned(),
+                        source: None,
+                    })
+                })
+                .collect::<Result<Vec<DatasetItem>, Error>>()?;
+            dataset.items.extend(items);
+        }
+        Ok(dataset)
+    }
+
+    fn write_dataset(&self, dataset: &Dataset, output: &Path) -> Result<(), Error> {
+        fs::create_dir_all(output)?;
+        let all_classes = dataset.all_classes();
+        let class_hash: HashMap<String, i64> = all_classes
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.to_owned(), idx as i64))
+            .collect();
+        for item in dataset.items.iter() {
+            let split_folder = match &item.split {
+                Some(split) => output.join(split),
+                None => output.to_path_buf(),
+            };
+            let file_name = item.image_path.file_name().unwrap();
+            copy_image(
+                &dataset.image_source(item),
+                &split_folder.join("images").join(file_name),
+            )?;
+            let label_folder = split_folder.join("labels");
+            fs::create_dir_all(&label_folder)?;
+            let labelme = item.to_labelme();
+            match self.segment {
+                true => write_yolo_seg_to_txt(
+                    labelme.to_yolo_seg(&class_hash)?,
+                    &label_folder.join(file_name),
+                )?,
+                false => {
+                    write_yolo_to_txt(labelme.to_yolo(&class_hash)?, &label_folder.join(file_name))?
+                }
+            }
+        }
+
+        // relative to `path` like ultralytics expects
+        let images_of = |split: &str| -> String {
+            match dataset.items.iter().any(|item| item.split.is_some()) {
+                true => format!("{}/images", split),
+                false => String::from("images"),
+            }
+        };
+        let data_yaml = DatasetInfo {
+            path: Some(std::path::absolute(output)?.to_string_lossy().to_string()),
+            train: images_of("train"),
+            val: images_of("val"),
+            test: images_of("test"),
+            nc: all_classes.len() as i64,
+            names: all_classes.to_owned(),
+        };
+        write_dataset_info(&data_yaml, output)?;
+        Ok(())
+    }
+}
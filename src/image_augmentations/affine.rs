@@ -0,0 +1,399 @@
+/* affine warps of an image and its shapes */
+use crate::label::{polygon_area, skip_unsupported, Shape, ShapeType};
+use anyhow::{anyhow, Error, Result};
+use clap::Args;
+use image::{DynamicImage, Rgba, RgbaImage};
+use rand::Rng;
+use rayon::prelude::*;
+
+/// color of the canvas uncovered by a warp
+pub const FILL_COLOR: Rgba<u8> = Rgba([114, 114, 114, 255]);
+
+/// `x' = a*x + b*y + c`, `y' = d*x + e*y + f` in labelme screen coordinates,
+/// pixel `i` covers `i..i+1`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Affine {
+    pub fn identity() -> Affine {
+        Affine {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 0.0,
+            e: 1.0,
+            f: 0.0,
+        }
+    }
+
+    pub fn translate(tx: f32, ty: f32) -> Affine {
+        Affine {
+            c: tx,
+            f: ty,
+            ..Affine::identity()
+        }
+    }
+
+    /// counterclockwise on screen (y points down) around the origin
+    pub fn rotate(degrees: f32) -> Affine {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Affine {
+            a: cos,
+            b: sin,
+            d: -sin,
+            e: cos,
+            ..Affine::identity()
+        }
+    }
+
+    pub fn scale(sx: f32, sy: f32) -> Affine {
+        Affine {
+            a: sx,
+            e: sy,
+            ..Affine::identity()
+        }
+    }
+
+    /// shears x along y by `x_degrees` and y along x by `y_degrees`
+    pub fn shear(x_degrees: f32, y_degrees: f32) -> Affine {
+        Affine {
+            b: x_degrees.to_radians().tan(),
+            d: y_degrees.to_radians().tan(),
+            ..Affine::identity()
+        }
+    }
+
+    /// `self` followed by `next`
+    pub fn then(&self, next: &Affine) -> Affine {
+        Affine {
+            a: next.a * self.a + next.b * self.d,
+            b: next.a * self.b + next.b * self.e,
+            c: next.a * self.c + next.b * self.f + next.c,
+            d: next.d * self.a + next.e * self.d,
+            e: next.d * self.b + next.e * self.e,
+            f: next.d * self.c + next.e * self.f + next.f,
+        }
+    }
+
+    /// none if the matrix squashes everything onto a line
+    pub fn inverse(&self) -> Option<Affine> {
+        let det = self.a * self.e - self.b * self.d;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        Some(Affine {
+            a: self.e / det,
+            b: -self.b / det,
+            c: (self.b * self.f - self.e * self.c) / det,
+            d: -self.d / det,
+            e: self.a / det,
+            f: (self.d * self.c - self.a * self.f) / det,
+        })
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.b * y + self.c,
+            self.d * x + self.e * y + self.f,
+        )
+    }
+}
+
+// ranges of the random affine augmentation, every value
+// is drawn uniformly from `-x..=x` (`1-x..=1+x` for the scale)
+#[derive(Args, Debug, Clone)]
+pub struct AffineOptions {
+    #[arg(long, default_value_t = 15.0)]
+    /// max rotation in degrees
+    pub degrees: f32,
+
+    #[arg(long, default_value_t = 0.2)]
+    /// max scale change, 0.2 zooms between 0.8x and 1.2x
+    pub scale: f32,
+
+    #[arg(long, default_value_t = 0.1)]
+    /// max shift as a fraction of the image size
+    pub translate: f32,
+
+    #[arg(long, default_value_t = 5.0)]
+    /// max shear in degrees
+    pub shear: f32,
+
+    #[arg(long, default_value_t = 0.25)]
+    /// shapes with less of their area left
+    /// on the image after the warp are dropped
+    pub min_visibility: f32,
+}
+
+impl Default for AffineOptions {
+    fn default() -> Self {
+        AffineOptions {
+            degrees: 15.0,
+            scale: 0.2,
+            translate: 0.1,
+            shear: 5.0,
+            min_visibility: 0.25,
+        }
+    }
+}
+
+/// uniform in `-max..=max`, 0 for a range that's turned off
+pub(crate) fn symmetric<R: Rng>(rng: &mut R, max: f32) -> f32 {
+    match max > 0.0 {
+        true => rng.gen_range(-max..=max),
+        false => 0.0,
+    }
+}
+
+impl AffineOptions {
+    /// a random warp around the center of a `width` x `height` image
+    pub fn random<R: Rng>(&self, rng: &mut R, width: u32, height: u32) -> Affine {
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let scale = 1.0 + symmetric(rng, self.scale);
+        Affine::translate(-cx, -cy)
+            .then(&Affine::shear(
+                symmetric(rng, self.shear),
+                symmetric(rng, self.shear),
+            ))
+            .then(&Affine::scale(scale, scale))
+            .then(&Affine::rotate(symmetric(rng, self.degrees)))
+            .then(&Affine::translate(
+                cx + symmetric(rng, self.translate) * width as f32,
+                cy + symmetric(rng, self.translate) * height as f32,
+            ))
+    }
+}
+
+fn sample(source: &RgbaImage, x: i64, y: i64) -> [f32; 4] {
+    if x < 0 || y < 0 || x >= source.width() as i64 || y >= source.height() as i64 {
+        return FILL_COLOR.0.map(|c| c as f32);
+    }
+    source.get_pixel(x as u32, y as u32).0.map(|c| c as f32)
+}
+
+/// warps `image` onto a `width` x `height` canvas with bilinear sampling,
+/// uncovered pixels get `FILL_COLOR`
+pub fn warp_image(
+    image: &DynamicImage,
+    affine: &Affine,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, Error> {
+    let inverse = affine
+        .inverse()
+        .ok_or(anyhow!("[error]::affine: {:?} can't be inverted", affine))?;
+    let source = image.to_rgba8();
+    let mut warped = RgbaImage::from_pixel(width, height, FILL_COLOR);
+    warped
+        .par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                // sample at pixel centers
+                let (sx, sy) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+                let (sx, sy) = (sx - 0.5, sy - 0.5);
+                let (x0, y0) = (sx.floor(), sy.floor());
+                let (fx, fy) = (sx - x0, sy - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let (p00, p10) = (sample(&source, x0, y0), sample(&source, x0 + 1, y0));
+                let (p01, p11) = (sample(&source, x0, y0 + 1), sample(&source, x0 + 1, y0 + 1));
+                for c in 0..4 {
+                    let top = p00[c] * (1.0 - fx) + p10[c] * fx;
+                    let bottom = p01[c] * (1.0 - fx) + p11[c] * fx;
+                    pixel[c] = (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+    Ok(DynamicImage::ImageRgba8(warped))
+}
+
+/// clips a polygon to `0..width`, `0..height` (sutherland-hodgman)
+pub fn clip_polygon(points: &[Vec<f32>], width: f32, height: f32) -> Vec<Vec<f32>> {
+    // (axis, bound, keep points below the bound)
+    let edges = [
+        (0, 0.0, false),
+        (0, width, true),
+        (1, 0.0, false),
+        (1, height, true),
+    ];
+    let mut clipped: Vec<Vec<f32>> = points.to_owned();
+    for (axis, bound, below) in edges {
+        let inside = |p: &Vec<f32>| match below {
+            true => p[axis] <= bound,
+            false => p[axis] >= bound,
+        };
+        let input = std::mem::take(&mut clipped);
+        for (idx, current) in input.iter().enumerate() {
+            let previous = &input[(idx + input.len() - 1) % input.len()];
+            if inside(current) != inside(previous) {
+                let t = (bound - previous[axis]) / (current[axis] - previous[axis]);
+                clipped.push(vec![
+                    previous[0] + t * (current[0] - previous[0]),
+                    previous[1] + t * (current[1] - previous[1]),
+                ]);
+            }
+            if inside(current) {
+                clipped.push(current.to_owned());
+            }
+        }
+    }
+    clipped
+}
+
+/// moves a shape through `affine` onto a `width` x `height` canvas.
+/// rectangles become the box around their warped corners, circles become
+/// polygons and polygons keep every warped vertex, all clipped to the canvas.
+/// none if less than `min_visibility` of the shape's area is left
+/// (or a point/line ends up outside)
+pub fn transform_shape(
+    shape: &Shape,
+    affine: &Affine,
+    width: f32,
+    height: f32,
+    min_visibility: f32,
+) -> Result<Option<Shape>, Error> {
+    let warp = |points: &[Vec<f32>]| -> Vec<Vec<f32>> {
+        points
+            .iter()
+            .map(|p| {
+                let (x, y) = affine.apply(p[0], p[1]);
+                vec![x, y]
+            })
+            .collect()
+    };
+    let mut transformed = shape.to_owned();
+    match shape.shape_type {
+        ShapeType::Rectangle | ShapeType::Polygon | ShapeType::Circle => {
+            let warped = warp(&shape.polygon()?);
+            let area = polygon_area(&warped);
+            let clipped = clip_polygon(&warped, width, height);
+            if clipped.len() < 3 || area <= 0.0 {
+                return Ok(None);
+            }
+            if polygon_area(&clipped) / area < min_visibility {
+                return Ok(None);
+            }
+            if shape.shape_type == ShapeType::Rectangle {
+                transformed.points = clipped;
+                let bbox = transformed.bbox()?;
+                transformed.update_points_from_xyxy(bbox);
+            } else {
+                transformed.shape_type = ShapeType::Polygon;
+                transformed.points = clipped;
+            }
+        }
+        ShapeType::Point | ShapeType::Line | ShapeType::LineStrip => {
+            let warped = warp(&shape.points);
+            let on_canvas =
+                |p: &Vec<f32>| (0.0..=width).contains(&p[0]) && (0.0..=height).contains(&p[1]);
+            if !warped.iter().all(on_canvas) {
+                return Ok(None);
+            }
+            transformed.points = warped;
+        }
+        // their geometry isn't known well enough to warp it
+        ShapeType::Other(_) => {
+            skip_unsupported(shape);
+            return Ok(None);
+        }
+    }
+    Ok(Some(transformed))
+}
+
+#[cfg(test)]
+mod test_affine {
+    use crate::image_augmentations::affine::*;
+    use std::collections::HashMap;
+
+    fn _shape(shape_type: ShapeType, points: Vec<Vec<f32>>) -> Shape {
+        Shape {
+            label: String::from("box"),
+            points,
+            group_id: None,
+            shape_type,
+            flags: Some(HashMap::new()),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn compose_and_invert() {
+        let _affine = Affine::translate(-50.0, -25.0)
+            .then(&Affine::rotate(90.0))
+            .then(&Affine::translate(25.0, 50.0));
+        // 90 degrees ccw on screen: right of the center ends up above it
+        let (_x, _y) = _affine.apply(60.0, 25.0);
+        assert!((_x - 25.0).abs() < 1e-4 && (_y - 40.0).abs() < 1e-4);
+        let (_x, _y) = _affine.inverse().unwrap().apply(_x, _y);
+        assert!((_x - 60.0).abs() < 1e-4 && (_y - 25.0).abs() < 1e-4);
+        assert!(Affine::scale(0.0, 1.0).inverse().is_none());
+    }
+
+    #[test]
+    fn shapes_are_clipped_and_dropped() {
+        let _shift = Affine::translate(60.0, 0.0);
+        let _rect = _shape(
+            ShapeType::Rectangle,
+            vec![vec![10.0, 10.0], vec![50.0, 30.0]],
+        );
+        // 30 of 40 pixels wide end up outside a 100 wide canvas
+        let _moved = transform_shape(&_rect, &_shift, 100.0, 100.0, 0.2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(_moved.points, vec![vec![70.0, 10.0], vec![100.0, 30.0]]);
+        assert!(transform_shape(&_rect, &_shift, 100.0, 100.0, 0.8)
+            .unwrap()
+            .is_none());
+
+        // a rotated box grows to hold its corners
+        let _rotate = Affine::translate(-30.0, -20.0)
+            .then(&Affine::rotate(45.0))
+            .then(&Affine::translate(30.0, 20.0));
+        let _rotated = transform_shape(&_rect, &_rotate, 100.0, 100.0, 0.0)
+            .unwrap()
+            .unwrap()
+            .bbox()
+            .unwrap();
+        assert!((_rotated.x2 - _rotated.x1 - 42.4264).abs() < 1e-3);
+
+        // polygons keep their warped vertices
+        let _triangle = _shape(
+            ShapeType::Polygon,
+            vec![vec![0.0, 0.0], vec![40.0, 0.0], vec![0.0, 40.0]],
+        );
+        let _moved = transform_shape(
+            &_triangle,
+            &Affine::translate(10.0, 10.0),
+            100.0,
+            100.0,
+            0.5,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            _moved.points,
+            vec![vec![10.0, 10.0], vec![50.0, 10.0], vec![10.0, 50.0]]
+        );
+        let _point = _shape(ShapeType::Point, vec![vec![90.0, 5.0]]);
+        assert!(transform_shape(&_point, &_shift, 100.0, 100.0, 0.0)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn warp_fills_uncovered_pixels() {
+        let _image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255])));
+        let _warped = warp_image(&_image, &Affine::translate(2.0, 0.0), 4, 2)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(*_warped.get_pixel(0, 0), FILL_COLOR);
+        assert_eq!(*_warped.get_pixel(3, 1), Rgba([255, 0, 0, 255]));
+    }
+}
@@ -1,11 +1,18 @@
+use super::affine::{transform_shape, warp_image, Affine, AffineOptions, FILL_COLOR};
+use super::occlusion::{
+    boxes_overlap, pixels_in, point_in_polygon, EraseFill, EraseTarget, OcclusionMask,
+    OcclusionOptions,
+};
+use super::photometric::{Photometric, PhotometricOptions};
 use crate::fileutils::{write_labelme_to_json, write_yolo_to_txt};
 use crate::image_utils::{dynimg2string_png, open_image};
-use crate::label::Shape;
 use crate::label::{CoordinateType, LabelmeAnnotation, Xyxy, YoloAnnotation};
-use anyhow::{Error, Result};
-use clap::Subcommand;
+use crate::label::{skip_unsupported, Shape, ShapeType};
+use anyhow::{anyhow, bail, Error, Result};
+use clap::Args;
 use image::imageops::colorops;
-use image::{self, imageops, DynamicImage, GenericImage, GenericImageView};
+use image::imageops::FilterType;
+use image::{self, imageops, DynamicImage, GenericImage, GenericImageView, Rgba, RgbaImage};
 use ndarray::prelude::*;
 use rand::prelude::*;
 use sorted_list::Tuples;
@@ -13,21 +20,97 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum AugmentationType {
     FlipHorizontal,
     FlipVeritcal,
     RandomBrightness,
     UnSharpen,
-    HueRotate30,
-    HueRotate60,
-    HueRotate90,
-    HueRotate120,
-    HueRotate180,
-    HueRotate210,
-    HueRotate270,
+    /// color, blur, noise or compression with random parameters
+    Photometric(Photometric),
     Grayscale,
     Rotate90,
+    /// random rotation, scale, translation and shear
+    Affine,
+    /// 4 images tiled around a random center
+    Mosaic,
+    /// 2 images blended together
+    MixUp,
+    /// a random window that keeps most shapes
+    RandomCrop,
+    /// a window around a random shape
+    ObjectCrop,
+    /// cutout / random erasing patches
+    Cutout,
+    /// objects of another image pasted in
+    CopyPaste,
+}
+
+// settings of the augmentations that combine several images
+#[derive(Args, Debug, Clone)]
+pub struct MultiImageOptions {
+    #[arg(long)]
+    /// width and height of a mosaic, the size of its first image if not given
+    pub mosaic_size: Option<u32>,
+
+    #[arg(long, default_value_t = 0.5)]
+    /// weight of the first image in a MixUp, the second one gets the rest
+    pub mixup_ratio: f32,
+}
+
+/// random windows `random_crop` tries before giving up
+const CROP_ATTEMPTS: usize = 20;
+
+/// random spots tried for an erased patch or a pasted object
+const PLACEMENT_ATTEMPTS: usize = 20;
+
+// settings of the crop and resize augmentations
+#[derive(Args, Debug, Clone)]
+pub struct CropOptions {
+    #[arg(long, default_value_t = 0.6)]
+    /// side of random and object crops as a fraction of the image side
+    pub crop_scale: f32,
+
+    #[arg(long, default_value_t = 0.5)]
+    /// share of the shapes a random crop has to keep
+    pub min_retained: f32,
+
+    #[arg(long, value_parser = parse_size)]
+    /// resize every output, `640` or `640x480`
+    pub resize: Option<(u32, u32)>,
+
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    /// keep the aspect ratio when resizing and pad the rest
+    pub letterbox: bool,
+
+    #[arg(long, default_value = "114,114,114", value_parser = parse_color)]
+    /// letterbox padding as `r,g,b`
+    pub pad_color: Rgba<u8>,
+}
+
+/// `640` or `640x480`
+pub fn parse_size(input: &str) -> Result<(u32, u32), String> {
+    let sizes: Vec<&str> = input.split('x').collect();
+    let parse = |size: &str| size.trim().parse::<u32>().ok().filter(|size| *size > 0);
+    match sizes.as_slice() {
+        [size] => parse(size).map(|size| (size, size)),
+        [width, height] => parse(width).zip(parse(height)),
+        _ => None,
+    }
+    .ok_or(format!("{:?} isn't a size like 640 or 640x480", input))
+}
+
+/// `r,g,b`
+pub fn parse_color(input: &str) -> Result<Rgba<u8>, String> {
+    let channels: Vec<u8> = input
+        .split(',')
+        .map(|c| c.trim().parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|e| format!("{:?} isn't a color like 114,114,114: {}", input, e))?;
+    match channels.as_slice() {
+        [r, g, b] => Ok(Rgba([*r, *g, *b, 255])),
+        _ => Err(format!("{:?} isn't a color like 114,114,114", input)),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -71,7 +154,7 @@ impl ImageAugmentation {
         img_path.push(&img_fname);
         label_path.push(format!("{}.json", &anno_uuid));
         self.image.save(&img_path)?;
-        self.coords.imageData = dynimg2string_png(&self.image)?;
+        self.coords.imageData = Some(dynimg2string_png(&self.image)?);
         self.coords.imagePath = img_fname.to_owned();
         let yolo_anno = self.coords.to_yolo(class_hash)?;
 
@@ -90,16 +173,156 @@ impl ImageAugmentation {
         }
     }
 
+    /// annotation for an image composed of others,
+    /// `imagePath` and `imageData` are set when it's written
+    fn combined(image: DynamicImage, template: &LabelmeAnnotation, shapes: Vec<Shape>) -> Self {
+        let (width, height) = image.dimensions();
+        let coords = LabelmeAnnotation {
+            version: template.version.to_owned(),
+            flags: Some(HashMap::new()),
+            extra: serde_json::Map::new(),
+            shapes,
+            imagePath: template.imagePath.to_owned(),
+            imageData: None,
+            imageWidth: width as i64,
+            imageHeight: height as i64,
+        };
+        ImageAugmentation::new(image, coords)
+    }
+
+    /// tiles 4 images around a random center in the middle half
+    /// of a `width` x `height` canvas, see `mosaic_at`
+    pub fn mosaic<R: Rng>(
+        tiles: &[ImageAugmentation],
+        width: u32,
+        height: u32,
+        min_visibility: f32,
+        rng: &mut R,
+    ) -> Result<ImageAugmentation, Error> {
+        let center = (
+            rng.gen_range(0.25..=0.75) * width as f32,
+            rng.gen_range(0.25..=0.75) * height as f32,
+        );
+        ImageAugmentation::mosaic_at(tiles, width, height, center, min_visibility)
+    }
+
+    /// yolo style mosaic: every tile is scaled to fit half the canvas and put
+    /// top left, top right, bottom left and bottom right of `center`, touching it.
+    /// whatever sticks out of the canvas is cut off, shapes are clipped
+    /// and dropped below `min_visibility` like in `affine`
+    pub fn mosaic_at(
+        tiles: &[ImageAugmentation],
+        width: u32,
+        height: u32,
+        center: (f32, f32),
+        min_visibility: f32,
+    ) -> Result<ImageAugmentation, Error> {
+        if tiles.len() != 4 {
+            bail!(
+                "[error]::augmentations: a mosaic needs 4 images, got {}",
+                tiles.len()
+            );
+        }
+        let mut canvas = RgbaImage::from_pixel(width, height, FILL_COLOR);
+        let mut shapes: Vec<Shape> = vec![];
+        for (idx, tile) in tiles.iter().enumerate() {
+            let (tile_width, tile_height) = tile.image.dimensions();
+            let scale = (width as f32 / 2.0 / tile_width as f32)
+                .min(height as f32 / 2.0 / tile_height as f32);
+            let scaled_width = (tile_width as f32 * scale).round().max(1.0);
+            let scaled_height = (tile_height as f32 * scale).round().max(1.0);
+            // top left corner of the tile
+            let x = match idx % 2 {
+                0 => center.0 - scaled_width,
+                _ => center.0,
+            }
+            .round();
+            let y = match idx / 2 {
+                0 => center.1 - scaled_height,
+                _ => center.1,
+            }
+            .round();
+            let resized = imageops::resize(
+                &tile.image,
+                scaled_width as u32,
+                scaled_height as u32,
+                FilterType::Triangle,
+            );
+            imageops::replace(&mut canvas, &resized, x as i64, y as i64);
+
+            let place = Affine::scale(
+                scaled_width / tile_width as f32,
+                scaled_height / tile_height as f32,
+            )
+            .then(&Affine::translate(x, y));
+            for shape in tile.coords.shapes.iter() {
+                if let Some(shape) =
+                    transform_shape(shape, &place, width as f32, height as f32, min_visibility)?
+                {
+                    shapes.push(shape);
+                }
+            }
+        }
+        Ok(ImageAugmentation::combined(
+            DynamicImage::ImageRgba8(canvas),
+            &tiles[0].coords,
+            shapes,
+        ))
+    }
+
+    /// yolo style MixUp: `ratio` of this image plus `1 - ratio` of `other`
+    /// stretched to the same size, the shapes of both are kept
+    pub fn mixup(&self, other: &ImageAugmentation, ratio: f32) -> Result<ImageAugmentation, Error> {
+        if !(0.0..=1.0).contains(&ratio) {
+            bail!(
+                "[error]::augmentations: MixUp ratio has to be between 0 and 1, got {}",
+                ratio
+            );
+        }
+        let (width, height) = self.image.dimensions();
+        let (other_width, other_height) = other.image.dimensions();
+        let mut blended = self.image.to_rgba8();
+        let stretched = imageops::resize(&other.image, width, height, FilterType::Triangle);
+        for (pixel, other_pixel) in blended.pixels_mut().zip(stretched.pixels()) {
+            for c in 0..4 {
+                pixel[c] =
+                    (pixel[c] as f32 * ratio + other_pixel[c] as f32 * (1.0 - ratio)).round() as u8;
+            }
+        }
+
+        let stretch = Affine::scale(
+            width as f32 / other_width as f32,
+            height as f32 / other_height as f32,
+        );
+        let mut shapes = self.coords.shapes.to_owned();
+        for shape in other.coords.shapes.iter() {
+            if let Some(shape) = transform_shape(shape, &stretch, width as f32, height as f32, 0.0)?
+            {
+                shapes.push(shape);
+            }
+        }
+        Ok(ImageAugmentation::combined(
+            DynamicImage::ImageRgba8(blended),
+            &self.coords,
+            shapes,
+        ))
+    }
+
     pub fn grayscale(&mut self) {
         let _gscale = colorops::grayscale_alpha(&self.image);
 
         self.image = DynamicImage::ImageLumaA8(_gscale);
     }
 
-    pub fn huerotate(&mut self, rotate_degree: i32) {
-        let _hrotate = colorops::huerotate(&self.image, rotate_degree);
-
-        self.image = DynamicImage::ImageRgba8(_hrotate);
+    /// a photometric augmentation with parameters drawn from `options`
+    pub fn photometric<R: Rng>(
+        &mut self,
+        kind: Photometric,
+        options: &PhotometricOptions,
+        rng: &mut R,
+    ) -> Result<(), Error> {
+        self.image = options.apply(kind, &self.image, rng)?;
+        Ok(())
     }
 
     pub fn unsharpen(&mut self, sigma: f32, threshold: i32) {
@@ -107,43 +330,430 @@ impl ImageAugmentation {
         self.image = DynamicImage::ImageRgba8(_unsharpen);
     }
 
-    /// full credit to stackoverflow guy for this python code! :)
-    ///
-    /// https://stackoverflow.com/questions/71960632/how-to-rotate-a-rectangle-bounding-box-together-with-an-image
-    /// ```code
-    /// # assuming [[x1,y1], [x2,y2]]
-    /// def rotate_90(bbox, img_width):
-    ///  xmin,ymin = bbox[0]
-    ///  xmax,ymax = bbox[1]
-    ///  new_xmin = ymin
-    ///  new_ymin = img_width-xmax
-    ///  new_xmax = ymax
-    ///  new_ymax = img_width-xmin
-    ///  return [[new_xmin, new_ymin],
-    ///         [new_xmax, new_ymax]]
-    /// ````
-    pub fn rotate_90_counterclockwise(&mut self) {
-        // this is a HACKy solution , but it works for now
-        // first rotate it by 90 degrees clockwise,
-        // then flip h and flip v.
-        //
-        // TODO: actual ccw function but not now :|
-        let mut _rotate_90_ccw = imageops::rotate90(&self.image);
-        _rotate_90_ccw = imageops::flip_vertical(&_rotate_90_ccw);
-        _rotate_90_ccw = imageops::flip_horizontal(&_rotate_90_ccw);
-        self.image = DynamicImage::ImageRgba8(_rotate_90_ccw);
+    /// rotates the image and its shapes by 90 degrees counterclockwise,
+    /// a point `(x, y)` ends up at `(y, width - x)`
+    pub fn rotate_90_counterclockwise(&mut self) -> Result<(), Error> {
+        let (width, height) = self.image.dimensions();
+        self.image = DynamicImage::ImageRgba8(imageops::rotate270(&self.image));
+        let rotate = Affine {
+            a: 0.0,
+            b: 1.0,
+            c: 0.0,
+            d: -1.0,
+            e: 0.0,
+            f: width as f32,
+        };
+        self.transform_shapes(&rotate, 0.0)?;
+        self.coords.imageWidth = height as i64;
+        self.coords.imageHeight = width as i64;
+        Ok(())
+    }
 
-        for shape in self.coords.shapes.iter_mut() {
-            let new_x1 = shape.points[0][1].to_owned();
-            let new_y1 = &self.image.dimensions().0 - shape.points[1][0] as u32;
-            let new_x2 = shape.points[1][1].to_owned();
-            let new_y2 = &self.image.dimensions().0 - shape.points[0][0] as u32;
+    /// warps the image and its shapes with `affine`, the canvas keeps its size.
+    /// shapes with less than `min_visibility` of their area left are dropped,
+    /// returns how many were dropped
+    pub fn affine(&mut self, affine: &Affine, min_visibility: f32) -> Result<usize, Error> {
+        let (width, height) = self.image.dimensions();
+        self.image = warp_image(&self.image, affine, width, height)?;
+        self.transform_shapes(affine, min_visibility)
+    }
+
+    /// a random `affine` within the ranges of `options`
+    pub fn random_affine(&mut self, options: &AffineOptions) -> Result<usize, Error> {
+        let (width, height) = self.image.dimensions();
+        let affine = options.random(&mut rand::thread_rng(), width, height);
+        self.affine(&affine, options.min_visibility)
+    }
+
+    /// moves every shape onto the current image, see `affine::transform_shape`
+    fn transform_shapes(&mut self, affine: &Affine, min_visibility: f32) -> Result<usize, Error> {
+        let (width, height) = self.image.dimensions();
+        let shape_count = self.coords.shapes.len();
+        let mut transformed: Vec<Shape> = vec![];
+        for shape in self.coords.shapes.iter() {
+            if let Some(shape) =
+                transform_shape(shape, affine, width as f32, height as f32, min_visibility)?
+            {
+                transformed.push(shape);
+            }
+        }
+        self.coords.shapes = transformed;
+        Ok(shape_count - self.coords.shapes.len())
+    }
+
+    /// cuts `width` x `height` out of the image at `(x, y)`, shapes are
+    /// clipped and dropped below `min_visibility` like in `affine`,
+    /// returns how many were dropped
+    pub fn crop(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        min_visibility: f32,
+    ) -> Result<usize, Error> {
+        let (image_width, image_height) = self.image.dimensions();
+        if width == 0 || height == 0 || x + width > image_width || y + height > image_height {
+            bail!(
+                "[error]::augmentations: a {}x{} crop at ({}, {}) doesn't fit the {}x{} image",
+                width,
+                height,
+                x,
+                y,
+                image_width,
+                image_height
+            );
+        }
+        self.image = self.image.crop_imm(x, y, width, height);
+        self.coords.imageWidth = width as i64;
+        self.coords.imageHeight = height as i64;
+        self.transform_shapes(&Affine::translate(-(x as f32), -(y as f32)), min_visibility)
+    }
 
-            shape.points[0][0] = new_x1;
-            shape.points[0][1] = new_y1 as f32;
-            shape.points[1][0] = new_x2;
-            shape.points[1][1] = new_y2 as f32;
+    /// crops a random `width` x `height` window (at most the image size)
+    /// that keeps at least `min_retained` of the shapes, `CROP_ATTEMPTS`
+    /// windows are tried. none if none of them did, the image is left alone then
+    pub fn random_crop<R: Rng>(
+        &mut self,
+        width: u32,
+        height: u32,
+        min_visibility: f32,
+        min_retained: f32,
+        rng: &mut R,
+    ) -> Result<Option<usize>, Error> {
+        let (image_width, image_height) = self.image.dimensions();
+        let (width, height) = (width.clamp(1, image_width), height.clamp(1, image_height));
+        let needed = (self.coords.shapes.len() as f32 * min_retained).ceil() as usize;
+        for _ in 0..CROP_ATTEMPTS {
+            let x = rng.gen_range(0..=image_width - width);
+            let y = rng.gen_range(0..=image_height - height);
+            let window = Affine::translate(-(x as f32), -(y as f32));
+            let mut kept = 0;
+            for shape in self.coords.shapes.iter() {
+                if transform_shape(shape, &window, width as f32, height as f32, min_visibility)?
+                    .is_some()
+                {
+                    kept += 1;
+                }
+            }
+            if kept >= needed {
+                return Ok(Some(self.crop(x, y, width, height, min_visibility)?));
+            }
         }
+        Ok(None)
+    }
+
+    /// crops a `width` x `height` window (at most the image size) centred on
+    /// the shape at `idx`, moved back inside the image where it would stick out
+    pub fn crop_around(
+        &mut self,
+        idx: usize,
+        width: u32,
+        height: u32,
+        min_visibility: f32,
+    ) -> Result<usize, Error> {
+        let center = self
+            .coords
+            .shapes
+            .get(idx)
+            .ok_or(anyhow!(
+                "[error]::augmentations: no shape {} to crop around, there are {}",
+                idx,
+                self.coords.shapes.len()
+            ))?
+            .bbox()?
+            .get_center_xy();
+        let (image_width, image_height) = self.image.dimensions();
+        let (width, height) = (width.clamp(1, image_width), height.clamp(1, image_height));
+        let x = (center[0] - width as f32 / 2.0)
+            .round()
+            .clamp(0.0, (image_width - width) as f32);
+        let y = (center[1] - height as f32 / 2.0)
+            .round()
+            .clamp(0.0, (image_height - height) as f32);
+        self.crop(x as u32, y as u32, width, height, min_visibility)
+    }
+
+    /// stretches the image and its shapes to `width` x `height`
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        let (image_width, image_height) = self.image.dimensions();
+        self.image = DynamicImage::ImageRgba8(imageops::resize(
+            &self.image,
+            width,
+            height,
+            FilterType::Triangle,
+        ));
+        self.coords.imageWidth = width as i64;
+        self.coords.imageHeight = height as i64;
+        self.transform_shapes(
+            &Affine::scale(
+                width as f32 / image_width as f32,
+                height as f32 / image_height as f32,
+            ),
+            0.0,
+        )?;
+        Ok(())
+    }
+
+    /// yolo style letterbox: scales the image to fit `width` x `height`
+    /// keeping its aspect ratio and centres it on a `fill` canvas
+    pub fn letterbox(&mut self, width: u32, height: u32, fill: Rgba<u8>) -> Result<(), Error> {
+        let (image_width, image_height) = self.image.dimensions();
+        let scale = (width as f32 / image_width as f32).min(height as f32 / image_height as f32);
+        let scaled_width = ((image_width as f32 * scale).round() as u32).clamp(1, width);
+        let scaled_height = ((image_height as f32 * scale).round() as u32).clamp(1, height);
+        let (pad_x, pad_y) = ((width - scaled_width) / 2, (height - scaled_height) / 2);
+        let mut canvas = RgbaImage::from_pixel(width, height, fill);
+        let resized = imageops::resize(
+            &self.image,
+            scaled_width,
+            scaled_height,
+            FilterType::Triangle,
+        );
+        imageops::replace(&mut canvas, &resized, pad_x as i64, pad_y as i64);
+        self.image = DynamicImage::ImageRgba8(canvas);
+        self.coords.imageWidth = width as i64;
+        self.coords.imageHeight = height as i64;
+        let place = Affine::scale(
+            scaled_width as f32 / image_width as f32,
+            scaled_height as f32 / image_height as f32,
+        )
+        .then(&Affine::translate(pad_x as f32, pad_y as f32));
+        self.transform_shapes(&place, 0.0)?;
+        Ok(())
+    }
+
+    /// fills `rects` (`x, y, width, height`) and drops or shrinks
+    /// the shapes they hide, see `occlude`. returns how many were dropped
+    pub fn erase_rects<R: Rng>(
+        &mut self,
+        rects: &[(u32, u32, u32, u32)],
+        fill: EraseFill,
+        max_occlusion: f32,
+        rng: &mut R,
+    ) -> Result<usize, Error> {
+        let mut image = self.image.to_rgba8();
+        let (width, height) = image.dimensions();
+        let mut mask = OcclusionMask::new(width, height);
+        for (x, y, w, h) in rects.iter() {
+            for py in *y..(y + h).min(height) {
+                for px in *x..(x + w).min(width) {
+                    let pixel = match fill {
+                        EraseFill::Gray => FILL_COLOR,
+                        EraseFill::Noise => Rgba([rng.gen(), rng.gen(), rng.gen(), 255]),
+                    };
+                    image.put_pixel(px, py, pixel);
+                    mask.hide(px, py);
+                }
+            }
+        }
+        self.image = DynamicImage::ImageRgba8(image);
+        let shape_count = self.coords.shapes.len();
+        self.occlude(&mask, shape_count, max_occlusion)
+    }
+
+    /// cutout / random erasing: up to `erase_count` patches, placed anywhere,
+    /// away from the shapes or on them depending on `erase_target`
+    pub fn random_erase<R: Rng>(
+        &mut self,
+        options: &OcclusionOptions,
+        rng: &mut R,
+    ) -> Result<usize, Error> {
+        let (width, height) = self.image.dimensions();
+        let mut boxes: Vec<Xyxy> = vec![];
+        for shape in self.coords.shapes.iter() {
+            if shape.shape_type.has_area() {
+                boxes.push(shape.bbox()?);
+            }
+        }
+        let max_scale = options.erase_scale.max(0.02);
+        let mut rects: Vec<(u32, u32, u32, u32)> = vec![];
+        for _ in 0..rng.gen_range(1..=options.erase_count.max(1)) {
+            for _ in 0..PLACEMENT_ATTEMPTS {
+                let w = ((width as f32 * rng.gen_range(0.02..=max_scale)).round() as u32)
+                    .clamp(1, width);
+                let h = ((height as f32 * rng.gen_range(0.02..=max_scale)).round() as u32)
+                    .clamp(1, height);
+                let (x, y) = match options.erase_target {
+                    EraseTarget::Target if !boxes.is_empty() => {
+                        let target = &boxes[rng.gen_range(0..boxes.len())];
+                        let cx = rng.gen_range(target.x1..=target.x2) - w as f32 / 2.0;
+                        let cy = rng.gen_range(target.y1..=target.y2) - h as f32 / 2.0;
+                        (
+                            cx.round().clamp(0.0, (width - w) as f32) as u32,
+                            cy.round().clamp(0.0, (height - h) as f32) as u32,
+                        )
+                    }
+                    _ => (rng.gen_range(0..=width - w), rng.gen_range(0..=height - h)),
+                };
+                let patch = Xyxy::new(
+                    CoordinateType::Screen,
+                    x as f32,
+                    y as f32,
+                    (x + w) as f32,
+                    (y + h) as f32,
+                );
+                if options.erase_target == EraseTarget::Avoid
+                    && boxes.iter().any(|b| boxes_overlap(b, &patch))
+                {
+                    continue;
+                }
+                rects.push((x, y, w, h));
+                break;
+            }
+        }
+        self.erase_rects(&rects, options.erase_fill, options.max_occlusion, rng)
+    }
+
+    /// pastes shapes of `source` with the top left corner of their box at
+    /// `(x, y)`, `placements` are `(shape index, x, y)`. only the pixels inside
+    /// a shape are copied, the shape is appended and the shapes it covers
+    /// lose what it hides like in `erase_rects`. returns how many were dropped
+    pub fn paste_objects(
+        &mut self,
+        source: &ImageAugmentation,
+        placements: &[(usize, i64, i64)],
+        max_occlusion: f32,
+    ) -> Result<usize, Error> {
+        let source_image = source.image.to_rgba8();
+        let mut image = self.image.to_rgba8();
+        let (width, height) = image.dimensions();
+        let mut mask = OcclusionMask::new(width, height);
+        let covered_count = self.coords.shapes.len();
+        for (idx, x, y) in placements.iter() {
+            let shape = source.coords.shapes.get(*idx).ok_or(anyhow!(
+                "[error]::augmentations: no shape {} to paste, there are {}",
+                idx,
+                source.coords.shapes.len()
+            ))?;
+            if !shape.shape_type.has_area() {
+                bail!(
+                    "[error]::augmentations: {:?} shape {:?} has no area to paste",
+                    shape.shape_type,
+                    shape.label
+                );
+            }
+            let outline = shape.polygon()?;
+            let bbox = shape.bbox()?;
+            let (bx, by) = (bbox.x1.floor(), bbox.y1.floor());
+            for (sx, sy) in pixels_in(&bbox, source_image.width(), source_image.height()) {
+                if !point_in_polygon(&outline, sx as f32 + 0.5, sy as f32 + 0.5) {
+                    continue;
+                }
+                let (dx, dy) = (sx as i64 - bx as i64 + x, sy as i64 - by as i64 + y);
+                if dx < 0 || dy < 0 || dx >= width as i64 || dy >= height as i64 {
+                    continue;
+                }
+                image.put_pixel(dx as u32, dy as u32, *source_image.get_pixel(sx, sy));
+                mask.hide(dx as u32, dy as u32);
+            }
+            let shift = Affine::translate(*x as f32 - bx, *y as f32 - by);
+            if let Some(shape) = transform_shape(
+                shape,
+                &shift,
+                width as f32,
+                height as f32,
+                1.0 - max_occlusion,
+            )? {
+                self.coords.shapes.push(shape);
+            }
+        }
+        self.image = DynamicImage::ImageRgba8(image);
+        self.occlude(&mask, covered_count, max_occlusion)
+    }
+
+    /// copy-paste: up to `paste_count` random shapes of `source` pasted at random
+    /// spots that fit them whole and don't overlap each other, see `paste_objects`.
+    /// returns how many were pasted
+    pub fn copy_paste<R: Rng>(
+        &mut self,
+        source: &ImageAugmentation,
+        options: &OcclusionOptions,
+        rng: &mut R,
+    ) -> Result<usize, Error> {
+        let (width, height) = self.image.dimensions();
+        let mut candidates: Vec<usize> = source
+            .coords
+            .shapes
+            .iter()
+            .enumerate()
+            .filter(|(_, shape)| shape.shape_type.has_area())
+            .map(|(idx, _)| idx)
+            .collect();
+        candidates.shuffle(rng);
+        let mut placements: Vec<(usize, i64, i64)> = vec![];
+        let mut taken: Vec<Xyxy> = vec![];
+        for idx in candidates.into_iter().take(options.paste_count as usize) {
+            let bbox = source.coords.shapes[idx].bbox()?;
+            let w = (bbox.x2.ceil() - bbox.x1.floor()).max(1.0) as u32;
+            let h = (bbox.y2.ceil() - bbox.y1.floor()).max(1.0) as u32;
+            if w >= width || h >= height {
+                continue;
+            }
+            for _ in 0..PLACEMENT_ATTEMPTS {
+                let (x, y) = (rng.gen_range(0..=width - w), rng.gen_range(0..=height - h));
+                let spot = Xyxy::new(
+                    CoordinateType::Screen,
+                    x as f32,
+                    y as f32,
+                    (x + w) as f32,
+                    (y + h) as f32,
+                );
+                if taken.iter().any(|t| boxes_overlap(t, &spot)) {
+                    continue;
+                }
+                taken.push(spot);
+                placements.push((idx, x as i64, y as i64));
+                break;
+            }
+        }
+        self.paste_objects(source, &placements, options.max_occlusion)?;
+        Ok(placements.len())
+    }
+
+    /// the first `count` shapes lose what `mask` hides: the ones with more than
+    /// `max_occlusion` of their box hidden are dropped, rectangles shrink to the
+    /// box around their visible pixels and hidden points are dropped.
+    /// lines are left alone. returns how many were dropped
+    fn occlude(
+        &mut self,
+        mask: &OcclusionMask,
+        count: usize,
+        max_occlusion: f32,
+    ) -> Result<usize, Error> {
+        let mut kept: Vec<Shape> = vec![];
+        let mut dropped = 0;
+        for (idx, shape) in self.coords.shapes.iter().enumerate() {
+            let mut shape = shape.to_owned();
+            let visible = match shape.shape_type {
+                _ if idx >= count => true,
+                ShapeType::Point => !shape
+                    .points
+                    .first()
+                    .is_some_and(|p| mask.is_hidden(p[0] as u32, p[1] as u32)),
+                ShapeType::Line | ShapeType::LineStrip | ShapeType::Other(_) => true,
+                _ => {
+                    let bbox = shape.bbox()?;
+                    let hidden = mask.hidden_share(&bbox);
+                    match mask.visible_extent(&bbox) {
+                        _ if hidden == 0.0 => true,
+                        Some(extent) if hidden <= max_occlusion => {
+                            if shape.shape_type == ShapeType::Rectangle {
+                                shape.update_points_from_xyxy(extent);
+                            }
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+            };
+            match visible {
+                true => kept.push(shape),
+                false => dropped += 1,
+            }
+        }
+        self.coords.shapes = kept;
+        Ok(dropped)
     }
 
     /// adds random amount of brightness in a given range
@@ -160,15 +770,17 @@ impl ImageAugmentation {
     /// ```text
     /// subtracts y coordinates by image height then
     /// multiplies the coords by `[[1 , -1], [1 , -1]]`
-    /// to flip along the y axis
+    /// to flip along the y axis, for every point of the shape
     /// ````
     pub fn flip_v(&mut self) {
         let flipped_v_image = imageops::flip_vertical(&self.image);
         self.image = DynamicImage::ImageRgba8(flipped_v_image);
+        self.coords.shapes.retain(|shape| !skip_unsupported(shape));
         for shape in self.coords.shapes.iter_mut() {
             // subtract y coord by height and mult by -1
-            shape.points[0][1] = (shape.points[0][1] - (self.image.dimensions().1 as f32)) * -1.0;
-            shape.points[1][1] = (shape.points[1][1] - (self.image.dimensions().1 as f32)) * -1.0;
+            for point in shape.points.iter_mut() {
+                point[1] = -(point[1] - (self.image.dimensions().1 as f32));
+            }
         }
     }
 
@@ -183,11 +795,13 @@ impl ImageAugmentation {
     pub fn flip_h(&mut self) {
         let flipped_h_image = imageops::flip_horizontal(&self.image);
         self.image = DynamicImage::ImageRgba8(flipped_h_image);
+        self.coords.shapes.retain(|shape| !skip_unsupported(shape));
         for shape in self.coords.shapes.iter_mut() {
             // subtract x coord by width and mult by -1
             // we dont use ndarrays here sir
-            shape.points[0][0] = (shape.points[0][0] - (self.image.dimensions().0 as f32)) * -1.0;
-            shape.points[1][0] = (shape.points[1][0] - (self.image.dimensions().0 as f32)) * -1.0;
+            for point in shape.points.iter_mut() {
+                point[0] = -(point[0] - (self.image.dimensions().0 as f32));
+            }
         }
     }
 }
@@ -196,8 +810,10 @@ impl ImageAugmentation {
 mod test_augmetations {
     use crate::fileutils::{get_all_classes, get_all_classes_hash, get_all_jsons};
     use crate::image_augmentations::augmentations;
+    use crate::image_augmentations::occlusion::{EraseFill, EraseTarget, OcclusionOptions};
     use crate::image_utils::*;
     use crate::label::{read_labels_from_file, Shape};
+    use rand::SeedableRng;
 
     #[test]
     fn test_flip() {
@@ -230,4 +846,276 @@ mod test_augmetations {
             panic!()
         }
     }
+
+    #[test]
+    fn test_rotate_90() {
+        let _image = image::DynamicImage::new_rgba8(200, 100);
+        let _rect = Shape {
+            label: String::from("box"),
+            points: vec![vec![150.0, 20.0], vec![190.0, 60.0]],
+            group_id: None,
+            shape_type: crate::label::ShapeType::Rectangle,
+            flags: None,
+            extra: serde_json::Map::new(),
+        };
+        let _labelme = crate::label::LabelmeAnnotation::new(
+            None,
+            vec![_rect],
+            String::from("wide.png"),
+            None,
+            200,
+            100,
+        );
+        let mut _aug = augmentations::ImageAugmentation::new(_image, _labelme);
+        _aug.rotate_90_counterclockwise().unwrap();
+        assert_eq!(image::GenericImageView::dimensions(&_aug.image), (100, 200));
+        assert_eq!(
+            (_aug.coords.imageWidth, _aug.coords.imageHeight),
+            (100, 200)
+        );
+        assert_eq!(
+            _aug.coords.shapes[0].points,
+            vec![vec![20.0, 10.0], vec![60.0, 50.0]]
+        );
+    }
+
+    fn _solid(width: u32, height: u32, color: [u8; 4]) -> augmentations::ImageAugmentation {
+        let _image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            width,
+            height,
+            image::Rgba(color),
+        ));
+        let _rect = Shape {
+            label: format!("{:?}", color),
+            points: vec![vec![0.0, 0.0], vec![width as f32, height as f32]],
+            group_id: None,
+            shape_type: crate::label::ShapeType::Rectangle,
+            flags: None,
+            extra: serde_json::Map::new(),
+        };
+        let _labelme = crate::label::LabelmeAnnotation::new(
+            None,
+            vec![_rect],
+            String::from("solid.png"),
+            None,
+            width as i64,
+            height as i64,
+        );
+        augmentations::ImageAugmentation::new(_image, _labelme)
+    }
+
+    #[test]
+    fn test_mosaic() {
+        let _tiles = vec![
+            _solid(100, 100, [255, 0, 0, 255]),
+            _solid(200, 100, [0, 255, 0, 255]),
+            _solid(100, 100, [0, 0, 255, 255]),
+            _solid(50, 50, [255, 255, 0, 255]),
+        ];
+        let _mosaic =
+            augmentations::ImageAugmentation::mosaic_at(&_tiles, 200, 200, (120.0, 80.0), 0.25)
+                .unwrap();
+        assert_eq!(
+            (_mosaic.coords.imageWidth, _mosaic.coords.imageHeight),
+            (200, 200)
+        );
+        let _pixels = _mosaic.image.to_rgba8();
+        assert_eq!(_pixels.get_pixel(110, 70).0, [255, 0, 0, 255]);
+        assert_eq!(_pixels.get_pixel(130, 70).0, [0, 255, 0, 255]);
+        assert_eq!(_pixels.get_pixel(110, 90).0, [0, 0, 255, 255]);
+        assert_eq!(_pixels.get_pixel(130, 90).0, [255, 255, 0, 255]);
+        let _points: Vec<Vec<Vec<f32>>> = _mosaic
+            .coords
+            .shapes
+            .iter()
+            .map(|s| s.points.to_owned())
+            .collect();
+        assert_eq!(
+            _points,
+            vec![
+                vec![vec![20.0, 0.0], vec![120.0, 80.0]],
+                // 200x100 scaled to 100x50, 20 of it is cut off on the right
+                vec![vec![120.0, 30.0], vec![200.0, 80.0]],
+                vec![vec![20.0, 80.0], vec![120.0, 180.0]],
+                // small tiles are scaled up as well
+                vec![vec![120.0, 80.0], vec![200.0, 180.0]],
+            ]
+        );
+        assert!(augmentations::ImageAugmentation::mosaic_at(
+            &_tiles[..3],
+            200,
+            200,
+            (100.0, 100.0),
+            0.25
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_mixup() {
+        let _first = _solid(100, 50, [200, 0, 0, 255]);
+        let _second = _solid(50, 50, [0, 100, 0, 255]);
+        let _mixed = _first.mixup(&_second, 0.5).unwrap();
+        assert_eq!(
+            _mixed.image.to_rgba8().get_pixel(10, 10).0,
+            [100, 50, 0, 255]
+        );
+        assert_eq!(_mixed.coords.shapes.len(), 2);
+        // the second image is stretched onto the first one
+        assert_eq!(
+            _mixed.coords.shapes[1].points,
+            vec![vec![0.0, 0.0], vec![100.0, 50.0]]
+        );
+        assert!(_first.mixup(&_second, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_crops() {
+        let mut _aug = _solid(200, 100, [255, 0, 0, 255]);
+        _aug.coords.shapes[0].points = vec![vec![10.0, 10.0], vec![30.0, 30.0]];
+        let mut _far = _aug.coords.shapes[0].to_owned();
+        _far.points = vec![vec![160.0, 60.0], vec![190.0, 90.0]];
+        _aug.coords.shapes.push(_far);
+
+        let mut _crop = _aug.to_owned();
+        assert_eq!(_crop.crop(20, 0, 100, 50, 0.25).unwrap(), 1);
+        assert_eq!(
+            (_crop.coords.imageWidth, _crop.coords.imageHeight),
+            (100, 50)
+        );
+        assert_eq!(
+            _crop.coords.shapes[0].points,
+            vec![vec![0.0, 10.0], vec![10.0, 30.0]]
+        );
+        assert!(_aug.to_owned().crop(150, 0, 100, 50, 0.25).is_err());
+
+        // centred on the second box, pushed back inside the image
+        let mut _around = _aug.to_owned();
+        _around.crop_around(1, 60, 60, 0.25).unwrap();
+        assert_eq!(_around.coords.shapes.len(), 1);
+        assert_eq!(
+            _around.coords.shapes[0].points,
+            vec![vec![20.0, 20.0], vec![50.0, 50.0]]
+        );
+
+        // a 40x40 window can't hold both boxes
+        let mut _rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut _random = _aug.to_owned();
+        assert!(_random
+            .random_crop(40, 40, 1.0, 1.0, &mut _rng)
+            .unwrap()
+            .is_none());
+        assert_eq!(_random.coords.imageWidth, 200);
+        let _dropped = _random
+            .random_crop(190, 100, 1.0, 1.0, &mut _rng)
+            .unwrap()
+            .unwrap();
+        assert_eq!((_dropped, _random.coords.shapes.len()), (0, 2));
+    }
+
+    #[test]
+    fn test_letterbox() {
+        let mut _aug = _solid(200, 100, [255, 0, 0, 255]);
+        _aug.letterbox(100, 100, image::Rgba([0, 0, 0, 255]))
+            .unwrap();
+        assert_eq!(
+            (_aug.coords.imageWidth, _aug.coords.imageHeight),
+            (100, 100)
+        );
+        assert_eq!(_aug.image.to_rgba8().get_pixel(50, 10).0, [0, 0, 0, 255]);
+        assert_eq!(_aug.image.to_rgba8().get_pixel(50, 50).0, [255, 0, 0, 255]);
+        assert_eq!(
+            _aug.coords.shapes[0].points,
+            vec![vec![0.0, 25.0], vec![100.0, 75.0]]
+        );
+        _aug.resize(50, 200).unwrap();
+        assert_eq!(
+            _aug.coords.shapes[0].points,
+            vec![vec![0.0, 50.0], vec![50.0, 150.0]]
+        );
+        assert_eq!(augmentations::parse_size("640x480"), Ok((640, 480)));
+        assert!(augmentations::parse_size("640x").is_err());
+        assert_eq!(
+            augmentations::parse_color("1, 2,3"),
+            Ok(image::Rgba([1, 2, 3, 255]))
+        );
+    }
+
+    #[test]
+    fn test_erase() {
+        let mut _aug = _solid(100, 100, [255, 0, 0, 255]);
+        _aug.coords.shapes[0].points = vec![vec![0.0, 0.0], vec![40.0, 40.0]];
+        let mut _hidden = _aug.coords.shapes[0].to_owned();
+        _hidden.points = vec![vec![60.0, 60.0], vec![80.0, 80.0]];
+        let mut _point = _aug.coords.shapes[0].to_owned();
+        _point.shape_type = crate::label::ShapeType::Point;
+        _point.points = vec![vec![90.0, 10.0]];
+        _aug.coords.shapes.extend([_hidden, _point]);
+
+        let mut _rng = rand::rngs::StdRng::seed_from_u64(5);
+        let _dropped = _aug
+            .erase_rects(
+                &[(0, 0, 20, 40), (60, 60, 20, 15), (85, 5, 10, 10)],
+                EraseFill::Gray,
+                0.6,
+                &mut _rng,
+            )
+            .unwrap();
+        assert_eq!(_dropped, 2);
+        // half of the first box is hidden, it shrinks to the other half
+        assert_eq!(
+            _aug.coords.shapes[0].points,
+            vec![vec![20.0, 0.0], vec![40.0, 40.0]]
+        );
+        assert_eq!(
+            _aug.image.to_rgba8().get_pixel(5, 5).0,
+            [114, 114, 114, 255]
+        );
+
+        let mut _avoid = _solid(100, 100, [255, 0, 0, 255]);
+        _avoid.coords.shapes[0].points = vec![vec![0.0, 0.0], vec![50.0, 100.0]];
+        let _options = OcclusionOptions {
+            erase_target: EraseTarget::Avoid,
+            ..Default::default()
+        };
+        assert_eq!(_avoid.random_erase(&_options, &mut _rng).unwrap(), 0);
+        assert_eq!(
+            _avoid.coords.shapes[0].points,
+            vec![vec![0.0, 0.0], vec![50.0, 100.0]]
+        );
+    }
+
+    #[test]
+    fn test_copy_paste() {
+        let mut _source = _solid(50, 50, [255, 0, 0, 255]);
+        _source.coords.shapes[0].shape_type = crate::label::ShapeType::Polygon;
+        _source.coords.shapes[0].points = vec![vec![0.0, 0.0], vec![20.0, 0.0], vec![0.0, 20.0]];
+        let mut _target = _solid(100, 100, [0, 0, 255, 255]);
+        _target.coords.shapes[0].points = vec![vec![0.0, 0.0], vec![40.0, 40.0]];
+
+        let mut _pasted = _target.to_owned();
+        assert_eq!(
+            _pasted
+                .paste_objects(&_source, &[(0, 10, 10)], 0.6)
+                .unwrap(),
+            0
+        );
+        let _pixels = _pasted.image.to_rgba8();
+        assert_eq!(_pixels.get_pixel(12, 12).0, [255, 0, 0, 255]);
+        assert_eq!(_pixels.get_pixel(28, 28).0, [0, 0, 255, 255]);
+        assert_eq!(_pasted.coords.shapes.len(), 2);
+        assert_eq!(
+            _pasted.coords.shapes[1].points,
+            vec![vec![10.0, 10.0], vec![30.0, 10.0], vec![10.0, 30.0]]
+        );
+        assert!(_pasted.paste_objects(&_source, &[(3, 0, 0)], 0.6).is_err());
+
+        let mut _rng = rand::rngs::StdRng::seed_from_u64(5);
+        let _options = OcclusionOptions::default();
+        assert_eq!(
+            _target.copy_paste(&_source, &_options, &mut _rng).unwrap(),
+            1
+        );
+        assert_eq!(_target.coords.shapes.len(), 2);
+    }
 }
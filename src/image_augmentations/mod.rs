@@ -1 +1,4 @@
+pub mod affine;
 pub mod augmentations;
+pub mod occlusion;
+pub mod photometric;
@@ -0,0 +1,159 @@
+/* cutout, random erasing and copy-paste, the pieces the shapes need */
+use crate::label::{CoordinateType, Xyxy};
+use clap::{Args, ValueEnum};
+
+/// where erased patches go
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum EraseTarget {
+    #[default]
+    Anywhere,
+    /// only next to the shapes, never on their boxes
+    Avoid,
+    /// centered inside the box of a random shape
+    Target,
+}
+
+/// what erased patches are filled with
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum EraseFill {
+    /// cutout, the gray of the other augmentations
+    #[default]
+    Gray,
+    /// random erasing, random pixels
+    Noise,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OcclusionOptions {
+    #[arg(long, default_value_t = 3)]
+    /// max patches erased per image
+    pub erase_count: u32,
+
+    #[arg(long, default_value_t = 0.25)]
+    /// max side of an erased patch as a fraction of the image side
+    pub erase_scale: f32,
+
+    #[arg(long, value_enum, default_value_t = EraseTarget::Anywhere)]
+    pub erase_target: EraseTarget,
+
+    #[arg(long, value_enum, default_value_t = EraseFill::Gray)]
+    pub erase_fill: EraseFill,
+
+    #[arg(long, default_value_t = 0.6)]
+    /// shapes with more of their box hidden by erased patches or
+    /// pasted objects are dropped, boxes shrink to what's left otherwise
+    pub max_occlusion: f32,
+
+    #[arg(long, default_value_t = 3)]
+    /// max objects copy-paste takes from another image
+    pub paste_count: u32,
+}
+
+impl Default for OcclusionOptions {
+    fn default() -> Self {
+        OcclusionOptions {
+            erase_count: 3,
+            erase_scale: 0.25,
+            erase_target: EraseTarget::Anywhere,
+            erase_fill: EraseFill::Gray,
+            max_occlusion: 0.6,
+            paste_count: 3,
+        }
+    }
+}
+
+/// pixels hidden by erased patches or pasted objects
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcclusionMask {
+    pub width: u32,
+    pub height: u32,
+    hidden: Vec<bool>,
+}
+
+impl OcclusionMask {
+    pub fn new(width: u32, height: u32) -> OcclusionMask {
+        OcclusionMask {
+            width,
+            height,
+            hidden: vec![false; width as usize * height as usize],
+        }
+    }
+
+    pub fn hide(&mut self, x: u32, y: u32) {
+        if x < self.width && y < self.height {
+            self.hidden[y as usize * self.width as usize + x as usize] = true;
+        }
+    }
+
+    pub fn is_hidden(&self, x: u32, y: u32) -> bool {
+        x < self.width
+            && y < self.height
+            && self.hidden[y as usize * self.width as usize + x as usize]
+    }
+
+    /// share of the pixels of `bbox` that are hidden
+    pub fn hidden_share(&self, bbox: &Xyxy) -> f32 {
+        let (mut total, mut hidden) = (0usize, 0usize);
+        for (x, y) in pixels_in(bbox, self.width, self.height) {
+            total += 1;
+            if self.is_hidden(x, y) {
+                hidden += 1;
+            }
+        }
+        match total {
+            0 => 0.0,
+            _ => hidden as f32 / total as f32,
+        }
+    }
+
+    /// `bbox` shrunk to the box around its visible pixels, none if all are hidden
+    pub fn visible_extent(&self, bbox: &Xyxy) -> Option<Xyxy> {
+        let mut extent: Option<(u32, u32, u32, u32)> = None;
+        for (x, y) in pixels_in(bbox, self.width, self.height) {
+            if self.is_hidden(x, y) {
+                continue;
+            }
+            extent = Some(match extent {
+                None => (x, y, x, y),
+                Some((x1, y1, x2, y2)) => (x1.min(x), y1.min(y), x2.max(x), y2.max(y)),
+            });
+        }
+        let (x1, y1, x2, y2) = extent?;
+        Some(Xyxy::new(
+            CoordinateType::Screen,
+            bbox.x1.max(x1 as f32),
+            bbox.y1.max(y1 as f32),
+            bbox.x2.min(x2 as f32 + 1.0),
+            bbox.y2.min(y2 as f32 + 1.0),
+        ))
+    }
+}
+
+/// pixels of a `width` x `height` image with their center inside `bbox`
+pub fn pixels_in(bbox: &Xyxy, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let range = |low: f32, high: f32, size: u32| {
+        let first = (low - 0.5).ceil().max(0.0) as u32;
+        let last = ((high - 0.5).floor() + 1.0).clamp(0.0, size as f32) as u32;
+        first..last.max(first)
+    };
+    let xs = range(bbox.x1, bbox.x2, width);
+    let ys = range(bbox.y1, bbox.y2, height);
+    ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+}
+
+/// even-odd test of a point against polygon vertices
+pub fn point_in_polygon(points: &[Vec<f32>], x: f32, y: f32) -> bool {
+    let mut inside = false;
+    for (idx, p) in points.iter().enumerate() {
+        let q = &points[(idx + 1) % points.len()];
+        if (p[1] > y) != (q[1] > y) && x < p[0] + (y - p[1]) / (q[1] - p[1]) * (q[0] - p[0]) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// true if two boxes share any area
+pub fn boxes_overlap(a: &Xyxy, b: &Xyxy) -> bool {
+    a.x1 < b.x2 && b.x1 < a.x2 && a.y1 < b.y2 && b.y1 < a.y2
+}
@@ -0,0 +1,396 @@
+/* color, blur, noise and compression augmentations, the shapes stay put */
+use super::affine::symmetric;
+use anyhow::{Error, Result};
+use clap::{Args, ValueEnum};
+use image::codecs::jpeg::JpegEncoder;
+use image::{imageops, DynamicImage, RgbaImage};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Photometric {
+    /// hue shift plus saturation and value gains
+    Hsv,
+    Contrast,
+    Gamma,
+    GaussianBlur,
+    MotionBlur,
+    GaussianNoise,
+    SaltAndPepper,
+    /// re-encodes the image as a jpeg
+    Jpeg,
+    /// swaps the red, green and blue channels around
+    ChannelShuffle,
+}
+
+// ranges the photometric augmentations draw their parameters from,
+// gains are drawn from `1-x..=1+x`
+#[derive(Args, Debug, Clone)]
+pub struct PhotometricOptions {
+    #[arg(long, default_value_t = 10.0)]
+    /// max hue shift in degrees
+    pub hue: f32,
+
+    #[arg(long, default_value_t = 0.5)]
+    /// max saturation gain change
+    pub saturation: f32,
+
+    #[arg(long, default_value_t = 0.3)]
+    /// max value (brightness) gain change
+    pub value: f32,
+
+    #[arg(long, default_value_t = 0.3)]
+    /// max contrast change
+    pub contrast: f32,
+
+    #[arg(long, default_value_t = 0.3)]
+    /// max gamma change
+    pub gamma: f32,
+
+    #[arg(long, default_value_t = 2.0)]
+    /// max sigma of the gaussian blur
+    pub blur_sigma: f32,
+
+    #[arg(long, default_value_t = 9)]
+    /// max length in pixels of the motion blur
+    pub motion_blur: u32,
+
+    #[arg(long, default_value_t = 10.0)]
+    /// max standard deviation of the gaussian noise
+    pub noise_sigma: f32,
+
+    #[arg(long, default_value_t = 0.02)]
+    /// max share of the pixels turned black or white
+    pub salt_and_pepper: f32,
+
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u8).range(1..=95))]
+    /// lowest jpeg quality, the quality is drawn up to 95
+    pub jpeg_quality: u8,
+}
+
+impl Default for PhotometricOptions {
+    fn default() -> Self {
+        PhotometricOptions {
+            hue: 10.0,
+            saturation: 0.5,
+            value: 0.3,
+            contrast: 0.3,
+            gamma: 0.3,
+            blur_sigma: 2.0,
+            motion_blur: 9,
+            noise_sigma: 10.0,
+            salt_and_pepper: 0.02,
+            jpeg_quality: 30,
+        }
+    }
+}
+
+fn up_to<R: Rng>(rng: &mut R, max: f32) -> f32 {
+    match max > 0.0 {
+        true => rng.gen_range(0.0..=max),
+        false => 0.0,
+    }
+}
+
+impl PhotometricOptions {
+    /// applies `kind` with parameters drawn from the ranges
+    pub fn apply<R: Rng>(
+        &self,
+        kind: Photometric,
+        image: &DynamicImage,
+        rng: &mut R,
+    ) -> Result<DynamicImage, Error> {
+        let image = image.to_rgba8();
+        let augmented = match kind {
+            Photometric::Hsv => hsv_jitter(
+                &image,
+                symmetric(rng, self.hue),
+                1.0 + symmetric(rng, self.saturation),
+                1.0 + symmetric(rng, self.value),
+            ),
+            Photometric::Contrast => adjust_contrast(&image, 1.0 + symmetric(rng, self.contrast)),
+            Photometric::Gamma => {
+                adjust_gamma(&image, (1.0 + symmetric(rng, self.gamma)).max(0.01))
+            }
+            Photometric::GaussianBlur => gaussian_blur(&image, up_to(rng, self.blur_sigma)),
+            Photometric::MotionBlur => motion_blur(
+                &image,
+                rng.gen_range(1..=self.motion_blur.max(1)),
+                rng.gen_range(0.0..180.0),
+            ),
+            Photometric::GaussianNoise => gaussian_noise(&image, up_to(rng, self.noise_sigma), rng),
+            Photometric::SaltAndPepper => {
+                salt_and_pepper(&image, up_to(rng, self.salt_and_pepper), rng)
+            }
+            Photometric::Jpeg => jpeg_artifacts(&image, rng.gen_range(self.jpeg_quality..=95))?,
+            Photometric::ChannelShuffle => {
+                let mut order = [0, 1, 2];
+                order.shuffle(rng);
+                channel_shuffle(&image, order)
+            }
+        };
+        Ok(DynamicImage::ImageRgba8(augmented))
+    }
+}
+
+/// runs `f` over every pixel of a copy of `image`, rows in parallel
+fn map_pixels<F>(image: &RgbaImage, f: F) -> RgbaImage
+where
+    F: Fn(&mut [u8]) + Sync,
+{
+    let mut mapped = image.clone();
+    mapped
+        .par_chunks_mut(image.width().max(1) as usize * 4)
+        .for_each(|row| row.chunks_mut(4).for_each(&f));
+    mapped
+}
+
+/// hue in degrees, saturation and value in `0..=1`
+fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = match max > 0.0 {
+        true => delta / max,
+        false => 0.0,
+    };
+    (hue, saturation, max)
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (f32, f32, f32) {
+    let chroma = value * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    (r + m, g + m, b + m)
+}
+
+/// shifts the hue by `hue_degrees` and multiplies saturation and value
+pub fn hsv_jitter(
+    image: &RgbaImage,
+    hue_degrees: f32,
+    saturation_gain: f32,
+    value_gain: f32,
+) -> RgbaImage {
+    map_pixels(image, |pixel| {
+        let (hue, saturation, value) = rgb_to_hsv(
+            pixel[0] as f32 / 255.0,
+            pixel[1] as f32 / 255.0,
+            pixel[2] as f32 / 255.0,
+        );
+        let (r, g, b) = hsv_to_rgb(
+            hue + hue_degrees,
+            (saturation * saturation_gain).clamp(0.0, 1.0),
+            (value * value_gain).clamp(0.0, 1.0),
+        );
+        for (c, channel) in [r, g, b].into_iter().enumerate() {
+            pixel[c] = (channel * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    })
+}
+
+/// scales the distance of every channel to the mean gray level by `factor`
+pub fn adjust_contrast(image: &RgbaImage, factor: f32) -> RgbaImage {
+    let pixel_count = (image.width() as f32 * image.height() as f32).max(1.0);
+    let mean = image
+        .pixels()
+        .map(|p| (p[0] as f32 + p[1] as f32 + p[2] as f32) / 3.0)
+        .sum::<f32>()
+        / pixel_count;
+    map_pixels(image, |pixel| {
+        for channel in pixel.iter_mut().take(3) {
+            *channel = ((*channel as f32 - mean) * factor + mean)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+    })
+}
+
+/// `255 * (c / 255)^gamma` for every channel, below 1 brightens
+pub fn adjust_gamma(image: &RgbaImage, gamma: f32) -> RgbaImage {
+    let table: Vec<u8> = (0..=255)
+        .map(|c| (255.0 * (c as f32 / 255.0).powf(gamma)).round() as u8)
+        .collect();
+    map_pixels(image, |pixel| {
+        for channel in pixel.iter_mut().take(3) {
+            *channel = table[*channel as usize];
+        }
+    })
+}
+
+/// gaussian blur, a sigma close to 0 leaves the image as is
+pub fn gaussian_blur(image: &RgbaImage, sigma: f32) -> RgbaImage {
+    match sigma < 0.1 {
+        true => image.clone(),
+        false => imageops::blur(image, sigma),
+    }
+}
+
+/// averages `length` pixels along a line at `degrees`, edges are repeated
+pub fn motion_blur(image: &RgbaImage, length: u32, degrees: f32) -> RgbaImage {
+    if length < 2 {
+        return image.clone();
+    }
+    let (dy, dx) = degrees.to_radians().sin_cos();
+    let offsets: Vec<(i64, i64)> = (0..length)
+        .map(|i| {
+            let t = i as f32 - (length - 1) as f32 / 2.0;
+            ((t * dx).round() as i64, (t * dy).round() as i64)
+        })
+        .collect();
+    let (width, height) = image.dimensions();
+    let mut blurred = image.clone();
+    blurred
+        .par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                let mut sum = [0.0f32; 4];
+                for (ox, oy) in offsets.iter() {
+                    let sx = (x as i64 + ox).clamp(0, width as i64 - 1) as u32;
+                    let sy = (y as i64 + oy).clamp(0, height as i64 - 1) as u32;
+                    let source = image.get_pixel(sx, sy);
+                    for c in 0..4 {
+                        sum[c] += source[c] as f32;
+                    }
+                }
+                for c in 0..4 {
+                    pixel[c] = (sum[c] / offsets.len() as f32).round() as u8;
+                }
+            }
+        });
+    blurred
+}
+
+/// standard normal sample (box-muller), rand 0.8 has no normal distribution
+fn standard_normal<R: Rng>(rng: &mut R) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+/// adds noise with standard deviation `sigma` to every channel
+pub fn gaussian_noise<R: Rng>(image: &RgbaImage, sigma: f32, rng: &mut R) -> RgbaImage {
+    let mut noisy = image.clone();
+    for pixel in noisy.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f32 + standard_normal(rng) * sigma)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+    }
+    noisy
+}
+
+/// turns `amount` of the pixels black or white
+pub fn salt_and_pepper<R: Rng>(image: &RgbaImage, amount: f32, rng: &mut R) -> RgbaImage {
+    let mut noisy = image.clone();
+    for pixel in noisy.pixels_mut() {
+        if rng.gen::<f32>() < amount {
+            let level = match rng.gen::<bool>() {
+                true => 255,
+                false => 0,
+            };
+            pixel[0] = level;
+            pixel[1] = level;
+            pixel[2] = level;
+        }
+    }
+    noisy
+}
+
+/// round trip through a jpeg of `quality` (1-100), the alpha channel is kept
+pub fn jpeg_artifacts(image: &RgbaImage, quality: u8) -> Result<RgbaImage, Error> {
+    let mut encoded: Vec<u8> = vec![];
+    JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100))
+        .encode_image(&DynamicImage::ImageRgba8(image.clone()).to_rgb8())?;
+    let decoded = image::load_from_memory(&encoded)?.to_rgba8();
+    let mut compressed = image.clone();
+    for (pixel, jpeg_pixel) in compressed.pixels_mut().zip(decoded.pixels()) {
+        pixel[0] = jpeg_pixel[0];
+        pixel[1] = jpeg_pixel[1];
+        pixel[2] = jpeg_pixel[2];
+    }
+    Ok(compressed)
+}
+
+/// rgb channel `c` of the output comes from channel `order[c]`
+pub fn channel_shuffle(image: &RgbaImage, order: [usize; 3]) -> RgbaImage {
+    map_pixels(image, |pixel| {
+        let rgb = [pixel[0], pixel[1], pixel[2]];
+        for c in 0..3 {
+            pixel[c] = rgb[order[c]];
+        }
+    })
+}
+
+#[cfg(test)]
+mod test_photometric {
+    use crate::image_augmentations::photometric::*;
+    use image::Rgba;
+    use rand::SeedableRng;
+
+    #[test]
+    fn photometric_ops() {
+        let mut _image = RgbaImage::from_pixel(8, 8, Rgba([200, 100, 50, 255]));
+        _image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+
+        assert_eq!(hsv_jitter(&_image, 0.0, 1.0, 1.0), _image);
+        // a third of the way around the hue circle moves red to green
+        assert_eq!(
+            hsv_jitter(&_image, 120.0, 1.0, 1.0).get_pixel(1, 1).0,
+            [50, 200, 100, 255]
+        );
+        assert_eq!(
+            hsv_jitter(&_image, 0.0, 0.0, 1.0).get_pixel(1, 1).0,
+            [200, 200, 200, 255]
+        );
+        assert_eq!(adjust_gamma(&_image, 1.0), _image);
+        assert_eq!(adjust_contrast(&_image, 1.0), _image);
+        // no contrast leaves the mean gray, (63 * 116.7 + 0) / 64
+        assert_eq!(adjust_contrast(&_image, 0.0).get_pixel(1, 1).0[0], 115);
+        assert_eq!(
+            channel_shuffle(&_image, [2, 0, 1]).get_pixel(1, 1).0,
+            [50, 200, 100, 255]
+        );
+
+        let _uniform = RgbaImage::from_pixel(8, 8, Rgba([10, 20, 30, 255]));
+        assert_eq!(motion_blur(&_uniform, 5, 30.0), _uniform);
+        let mut _rng = rand::rngs::StdRng::seed_from_u64(3);
+        let _peppered = salt_and_pepper(&_uniform, 1.0, &mut _rng);
+        assert!(_peppered
+            .pixels()
+            .all(|p| p.0 == [0, 0, 0, 255] || p.0 == [255, 255, 255, 255]));
+        assert_eq!(gaussian_noise(&_uniform, 0.0, &mut _rng), _uniform);
+        let _jpeg = jpeg_artifacts(&_uniform, 50).unwrap();
+        assert_eq!(_jpeg.dimensions(), (8, 8));
+        assert!(_jpeg
+            .pixels()
+            .all(|p| p.0[0].abs_diff(10) <= 3 && p.0[3] == 255));
+
+        let _options = PhotometricOptions::default();
+        for _kind in Photometric::value_variants() {
+            let _augmented = _options
+                .apply(*_kind, &DynamicImage::ImageRgba8(_image.clone()), &mut _rng)
+                .unwrap();
+            assert_eq!(_augmented.width(), 8);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{bail, Error, Result};
+use clap::{Args, Parser, Subcommand};
+use std::env;
+use std::path::PathBuf;
+use std::process::{exit, Command};
+
+/// every kesa tool behind one binary,
+/// `kesa <command> --help` shows the flags of each command
+#[derive(Parser, Debug)]
+#[command(name = "kesa")]
+struct CliArguments {
+    #[command(subcommand)]
+    command: KesaCommand,
+}
+
+/// every command runs the matching `kesa_<command>` binary
+/// with the remaining arguments untouched
+#[derive(Subcommand, Debug)]
+enum KesaCommand {
+    /// auto labeling with an onnx or torch model
+    #[command(disable_help_flag = true)]
+    Al(ForwardedArgs),
+    /// converts between labelme, yolo, yolo-seg, coco and voc,
+    /// the input format is detected if `--input-format` is missing
+    #[command(disable_help_flag = true)]
+    Convert(ForwardedArgs),
+    /// labelme (or voc) to yolo txt
+    #[command(disable_help_flag = true)]
+    L2y(ForwardedArgs),
+    /// yolo txt back to labelme
+    #[command(disable_help_flag = true)]
+    Y2l(ForwardedArgs),
+    /// separates images and labels into train, val and test
+    #[command(disable_help_flag = true)]
+    Split(ForwardedArgs),
+    /// writes empty labelme jsons for unlabeled images
+    #[command(disable_help_flag = true)]
+    Fill(ForwardedArgs),
+    /// image augmentations from labelme annotations
+    #[command(disable_help_flag = true)]
+    Aug(ForwardedArgs),
+    /// labelme to coco and back
+    #[command(disable_help_flag = true)]
+    Coco(ForwardedArgs),
+    /// labelme to pascal voc and back
+    #[command(disable_help_flag = true)]
+    Voc(ForwardedArgs),
+    /// checks a dataset for broken labels and images
+    #[command(disable_help_flag = true)]
+    Validate(ForwardedArgs),
+    /// fixes the problems `validate` finds, in place or into a new folder
+    #[command(disable_help_flag = true)]
+    Repair(ForwardedArgs),
+    /// class counts, box size histograms and charts of a dataset
+    #[command(disable_help_flag = true)]
+    Stats(ForwardedArgs),
+    /// renames, merges, drops and reorders classes from a mapping yaml
+    #[command(disable_help_flag = true)]
+    Remap(ForwardedArgs),
+    /// combines several datasets into one
+    #[command(disable_help_flag = true)]
+    Merge(ForwardedArgs),
+    /// finds exact and near duplicate images
+    #[command(disable_help_flag = true)]
+    Dedup(ForwardedArgs),
+}
+
+#[derive(Args, Debug)]
+struct ForwardedArgs {
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl KesaCommand {
+    fn binary_and_args(&self) -> (&'static str, &Vec<String>) {
+        match self {
+            KesaCommand::Al(forwarded) => ("kesa_al", &forwarded.args),
+            KesaCommand::Convert(forwarded) => ("kesa_convert", &forwarded.args),
+            KesaCommand::L2y(forwarded) => ("kesa_l2y", &forwarded.args),
+            KesaCommand::Y2l(forwarded) => ("kesa_y2l", &forwarded.args),
+            KesaCommand::Split(forwarded) => ("kesa_split", &forwarded.args),
+            KesaCommand::Fill(forwarded) => ("kesa_fill", &forwarded.args),
+            KesaCommand::Aug(forwarded) => ("kesa_aug", &forwarded.args),
+            KesaCommand::Coco(forwarded) => ("kesa_coco", &forwarded.args),
+            KesaCommand::Voc(forwarded) => ("kesa_voc", &forwarded.args),
+            KesaCommand::Validate(forwarded) => ("kesa_validate", &forwarded.args),
+            KesaCommand::Repair(forwarded) => ("kesa_repair", &forwarded.args),
+            KesaCommand::Stats(forwarded) => ("kesa_stats", &forwarded.args),
+            KesaCommand::Remap(forwarded) => ("kesa_remap", &forwarded.args),
+            KesaCommand::Merge(forwarded) => ("kesa_merge", &forwarded.args),
+            KesaCommand::Dedup(forwarded) => ("kesa_dedup", &forwarded.args),
+        }
+    }
+}
+
+/// the tools are installed next to `kesa`,
+/// falls back to `PATH` if they aren't
+fn find_binary(name: &str) -> PathBuf {
+    let file_name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    match env::current_exe() {
+        Ok(current) => {
+            let sibling = current.with_file_name(&file_name);
+            match sibling.exists() {
+                true => sibling,
+                false => PathBuf::from(file_name),
+            }
+        }
+        Err(_) => PathBuf::from(file_name),
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let args = CliArguments::parse();
+    let (name, forwarded) = args.command.binary_and_args();
+    let binary = find_binary(name);
+    let status = match Command::new(&binary).args(forwarded).status() {
+        Ok(status) => status,
+        Err(e) => bail!(
+            "[error]::kesa: failed to run {:?}, is it built/installed? ({})",
+            binary,
+            e
+        ),
+    };
+    exit(status.code().unwrap_or(1));
+}
@@ -4,7 +4,6 @@ mod image_utils;
 mod label;
 mod model;
 mod output;
-mod plotting;
 mod splash;
 use crate::{
     backends::{candle_backend::CandleModel, compute_backends::InferenceModel},
@@ -30,7 +29,6 @@ use indicatif::ProgressBar;
 use label::{Embeddings, Shape, YoloAnnotation};
 use lazy_static::lazy_static;
 use ndarray::{s, ArrayBase, Axis, Dim, IxDynImpl, OwnedRepr};
-use plotting::draw_dummy_graph;
 use rayon::prelude::*;
 use spinners::{Spinner, Spinners};
 use splash::print_splash;
@@ -322,7 +320,6 @@ fn main() -> Result<(), Error> {
         }
         _ => panic!("[error]::kesa_al: cannot infer model type!"),
     };
-    // draw_dummy_graph();
     Ok(())
 }
 
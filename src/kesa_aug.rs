@@ -5,18 +5,25 @@ mod label;
 mod output;
 mod splash;
 
-use anyhow::{bail, Error, Result};
-use clap::Parser;
+use anyhow::{anyhow, Error, Result};
+use clap::{Parser, ValueEnum};
 use fileutils::{get_all_classes, open_image, ExportFolderOptions};
-use image::DynamicImage;
-use image_augmentations::augmentations::{AugmentationType, ImageAugmentation};
+use image::GenericImageView;
+use image_augmentations::affine::AffineOptions;
+use image_augmentations::augmentations::{
+    AugmentationType, CropOptions, ImageAugmentation, MultiImageOptions,
+};
+use image_augmentations::occlusion::OcclusionOptions;
+use image_augmentations::photometric::{Photometric, PhotometricOptions};
 use indicatif::ProgressBar;
 use label::{read_labels_from_file, LabelmeAnnotation};
-use rand::distributions::{Distribution, Uniform};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use rayon::prelude::*;
 use spinoff::{spinners, Color, Spinner};
 use splash::print_splash;
 use std::collections::HashMap;
+use std::path::Path;
 use std::{fs, path::PathBuf};
 
 use crate::fileutils::{get_all_classes_hash, get_all_jsons, write_data_yaml, write_yolo_to_txt};
@@ -38,6 +45,21 @@ struct CliArguments {
     /// image variations to create
     /// by default is 5 times
     variations: Option<i32>,
+
+    #[command(flatten)]
+    affine: AffineOptions,
+
+    #[command(flatten)]
+    multi_image: MultiImageOptions,
+
+    #[command(flatten)]
+    crop: CropOptions,
+
+    #[command(flatten)]
+    photometric: PhotometricOptions,
+
+    #[command(flatten)]
+    occlusion: OcclusionOptions,
 }
 
 fn main() -> Result<(), Error> {
@@ -55,10 +77,9 @@ fn main() -> Result<(), Error> {
     };
 
     let export_format = match &args.format {
-        Some(ref String) => args.format,
-        None => Some(String::from("labelme")),
-    }
-    .unwrap();
+        Some(format) => format.to_owned(),
+        None => String::from("labelme"),
+    };
 
     println!("export format {:?}", &export_format);
 
@@ -86,7 +107,14 @@ fn main() -> Result<(), Error> {
             let do_aug = get_random_aug().unwrap();
 
             // FUCK THEM <<RESULT>> HANDLING KIDS
-            create_augmentation(do_aug, &file, &classes_hash, &export_format, &args.folder)
+            create_augmentation(
+                do_aug,
+                file,
+                &classes_hash,
+                &export_format,
+                &all_json,
+                &args,
+            )
                 .unwrap();
         }
     });
@@ -99,19 +127,14 @@ fn create_augmentation(
     json_path: &PathBuf,
     class_hash: &HashMap<String, i64>,
     export_format: &str,
-    folder: &str,
+    all_json: &[PathBuf],
+    args: &CliArguments,
 ) -> Result<(), Error> {
-    let label = read_labels_from_file(json_path.to_str().unwrap())?;
-
-    let mut img_path = PathBuf::from(folder);
-    img_path.push(&label.imagePath);
-
-    let img = open_image(&img_path)?;
-
-    let mut aug = ImageAugmentation {
-        image: img,
-        coords: label,
-    };
+    // augmentations land next to the json they were made from
+    let folder = json_path.parent().unwrap_or(Path::new(&args.folder));
+    let (affine_options, multi_options, crop_options) =
+        (&args.affine, &args.multi_image, &args.crop);
+    let mut aug = load_augmentation(json_path)?;
     match &aug_type {
         AugmentationType::FlipVeritcal => {
             aug.flip_v();
@@ -125,58 +148,134 @@ fn create_augmentation(
         AugmentationType::UnSharpen => {
             aug.unsharpen(10.0, 2);
         }
-        AugmentationType::HueRotate30 => {
-            aug.huerotate(30);
+        AugmentationType::Photometric(kind) => {
+            aug.photometric(*kind, &args.photometric, &mut rand::thread_rng())?;
         }
-        AugmentationType::HueRotate60 => {
-            aug.huerotate(60);
+        AugmentationType::Grayscale => {
+            aug.grayscale();
         }
-        AugmentationType::HueRotate90 => {
-            aug.huerotate(90);
+        AugmentationType::Rotate90 => {
+            aug.rotate_90_counterclockwise()?;
         }
-        AugmentationType::HueRotate120 => {
-            aug.huerotate(120);
+        AugmentationType::Affine => {
+            aug.random_affine(affine_options)?;
         }
-        AugmentationType::HueRotate180 => {
-            aug.huerotate(180);
+        AugmentationType::Mosaic => {
+            let mut rng = rand::thread_rng();
+            let (width, height) = match multi_options.mosaic_size {
+                Some(size) => (size, size),
+                None => aug.image.dimensions(),
+            };
+            let mut tiles = vec![aug];
+            for partner in all_json.choose_multiple(&mut rng, 3) {
+                tiles.push(load_augmentation(partner)?);
+            }
+            // fewer than 4 labelled images, repeat the first one
+            while tiles.len() < 4 {
+                tiles.push(tiles[0].to_owned());
+            }
+            aug = ImageAugmentation::mosaic(
+                &tiles,
+                width,
+                height,
+                affine_options.min_visibility,
+                &mut rng,
+            )?;
         }
-        AugmentationType::HueRotate210 => {
-            aug.huerotate(210);
+        AugmentationType::MixUp => {
+            let partner = all_json
+                .choose(&mut rand::thread_rng())
+                .unwrap_or(json_path);
+            aug = aug.mixup(
+                &load_augmentation(partner)?,
+                multi_options.mixup_ratio,
+            )?;
         }
-        AugmentationType::HueRotate270 => {
-            aug.huerotate(270);
+        AugmentationType::RandomCrop => {
+            let (width, height) = aug.image.dimensions();
+            aug.random_crop(
+                (width as f32 * crop_options.crop_scale) as u32,
+                (height as f32 * crop_options.crop_scale) as u32,
+                affine_options.min_visibility,
+                crop_options.min_retained,
+                &mut rand::thread_rng(),
+            )?;
         }
-        AugmentationType::Grayscale => {
-            aug.grayscale();
+        AugmentationType::Cutout => {
+            aug.random_erase(&args.occlusion, &mut rand::thread_rng())?;
         }
-        AugmentationType::Rotate90 => {
-            aug.rotate_90_counterclockwise();
+        AugmentationType::CopyPaste => {
+            let mut rng = rand::thread_rng();
+            let partner = all_json.choose(&mut rng).unwrap_or(json_path);
+            aug.copy_paste(
+                &load_augmentation(partner)?,
+                &args.occlusion,
+                &mut rng,
+            )?;
+        }
+        AugmentationType::ObjectCrop => {
+            let (width, height) = aug.image.dimensions();
+            if !aug.coords.shapes.is_empty() {
+                let idx = rand::thread_rng().gen_range(0..aug.coords.shapes.len());
+                aug.crop_around(
+                    idx,
+                    (width as f32 * crop_options.crop_scale) as u32,
+                    (height as f32 * crop_options.crop_scale) as u32,
+                    affine_options.min_visibility,
+                )?;
+            }
         }
     }
-    aug.write_annotations(&PathBuf::from(folder), class_hash)?;
+    if let Some((width, height)) = crop_options.resize {
+        match crop_options.letterbox {
+            true => aug.letterbox(width, height, crop_options.pad_color)?,
+            false => aug.resize(width, height)?,
+        }
+    }
+    aug.write_annotations(&folder.to_path_buf(), class_hash)?;
     Ok(())
 }
 
+/// a labelme json and its image, `imagePath` is relative to the json
+fn load_augmentation(json_path: &Path) -> Result<ImageAugmentation, Error> {
+    let label = read_labels_from_file(json_path.to_str().unwrap())?;
+
+    let img_path = json_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&label.imagePath);
+
+    let img = open_image(&img_path)?;
+
+    Ok(ImageAugmentation {
+        image: img,
+        coords: label,
+    })
+}
+
 fn get_random_aug() -> Result<AugmentationType, Error> {
-    let mut rng = rand::thread_rng();
-    // get random number that
-    // corresponds toa  augmentation type
-    let aug_t = Uniform::from(0..13).sample(&mut rng);
-    let do_aug = match aug_t {
-        0 => AugmentationType::FlipHorizontal,
-        1 => AugmentationType::FlipVeritcal,
-        2 => AugmentationType::RandomBrightness,
-        3 => AugmentationType::UnSharpen,
-        4 => AugmentationType::HueRotate30,
-        5 => AugmentationType::HueRotate60,
-        6 => AugmentationType::HueRotate90,
-        7 => AugmentationType::HueRotate120,
-        8 => AugmentationType::HueRotate180,
-        9 => AugmentationType::HueRotate210,
-        10 => AugmentationType::HueRotate270,
-        11 => AugmentationType::Grayscale,
-        12 => AugmentationType::Rotate90,
-        _ => panic!("[error]::kesa_aug: unknown augmentation type!"),
-    };
-    Ok(do_aug)
+    let mut choices = vec![
+        AugmentationType::FlipHorizontal,
+        AugmentationType::FlipVeritcal,
+        AugmentationType::RandomBrightness,
+        AugmentationType::UnSharpen,
+        AugmentationType::Grayscale,
+        AugmentationType::Rotate90,
+        AugmentationType::Affine,
+        AugmentationType::Mosaic,
+        AugmentationType::MixUp,
+        AugmentationType::RandomCrop,
+        AugmentationType::ObjectCrop,
+        AugmentationType::Cutout,
+        AugmentationType::CopyPaste,
+    ];
+    choices.extend(
+        Photometric::value_variants()
+            .iter()
+            .map(|kind| AugmentationType::Photometric(*kind)),
+    );
+    choices
+        .choose(&mut rand::thread_rng())
+        .copied()
+        .ok_or(anyhow!("[error]::kesa_aug: no augmentation to pick from"))
 }
\ No newline at end of file
@@ -0,0 +1,84 @@
+mod coco;
+mod fileutils;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+
+use anyhow::{Error, Result};
+use clap::{ArgAction, Parser};
+use coco::{read_coco_from_file, write_coco_to_json, write_coco_to_labelme, CocoDataset};
+use fileutils::{get_all_classes, get_all_jsons};
+use spinoff::{spinners, Color, Spinner};
+use splash::print_splash;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    /// labelme folder, or the image folder
+    /// the coco `file_name`s are relative to
+    folder: String,
+
+    #[arg(long)]
+    /// coco json to explode into labelme jsons,
+    /// if not supplied the labelme folder is
+    /// exported to coco instead
+    coco: Option<String>,
+
+    #[arg(long)]
+    /// coco json to write,
+    /// defaults to `instances.json`
+    export: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// embed the image into the labelme jsons (`imageData`)
+    image_data: bool,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+
+    match &args.coco {
+        Some(coco_file) => {
+            let mut spinner = Spinner::new(
+                spinners::Hearts,
+                format!("[info]::kesa_coco: reading {:?}", coco_file),
+                Color::White,
+            );
+            let coco = read_coco_from_file(&PathBuf::from(coco_file))?;
+            let written =
+                write_coco_to_labelme(&coco, &PathBuf::from(&args.folder), args.image_data)?;
+            spinner
+                .success(format!("[info]::kesa_coco: wrote {:?} labelme jsons", written).as_str());
+        }
+        None => {
+            let export = args
+                .export
+                .unwrap_or_else(|| String::from("instances.json"));
+            let mut spinner = Spinner::new(
+                spinners::Hearts,
+                format!(
+                    "[info]::kesa_coco: searching for .json files in {:?}",
+                    &args.folder
+                ),
+                Color::White,
+            );
+            let all_json = get_all_jsons(&args.folder)?;
+            let all_classes = get_all_classes(&all_json)?;
+            let coco = CocoDataset::from_labelme(Path::new(&args.folder), &all_json, &all_classes)?;
+            write_coco_to_json(&coco, &PathBuf::from(&export))?;
+            spinner.success(
+                format!(
+                    "[info]::kesa_coco: wrote {:?} images, {:?} annotations to {:?}",
+                    coco.images.len(),
+                    coco.annotations.len(),
+                    &export
+                )
+                .as_str(),
+            );
+        }
+    }
+    Ok(())
+}
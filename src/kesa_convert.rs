@@ -0,0 +1,77 @@
+mod coco;
+mod dataset;
+mod fileutils;
+mod formats;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+mod voc;
+
+use anyhow::{Error, Result};
+use clap::Parser;
+use formats::annotation_format::{detect_format, get_format, FORMAT_NAMES};
+use spinoff::{spinners, Color, Spinner};
+use splash::print_splash;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long)]
+    /// labelme, yolo, yolo-seg, coco or voc,
+    /// detected from the folder if not supplied
+    input_format: Option<String>,
+
+    #[arg(long)]
+    /// labelme, yolo, yolo-seg, coco or voc
+    output_format: String,
+
+    #[arg(long)]
+    /// defaults to `export`
+    export: Option<String>,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let export = PathBuf::from(args.export.unwrap_or_else(|| String::from("export")));
+
+    let input_format = match &args.input_format {
+        Some(input_format) => get_format(input_format)?,
+        None => get_format(detect_format(&PathBuf::from(&args.folder))?)?,
+    };
+    let output_format = get_format(&args.output_format)?;
+    println!(
+        "[info]::kesa_convert: {} -> {} (supported: {:?})",
+        input_format.name(),
+        output_format.name(),
+        FORMAT_NAMES
+    );
+
+    let mut spinner = Spinner::new(
+        spinners::Hearts,
+        format!("[info]::kesa_convert: reading {:?}", &args.folder),
+        Color::White,
+    );
+    let dataset = input_format.read_dataset(&PathBuf::from(&args.folder))?;
+    spinner.success(
+        format!(
+            "[info]::kesa_convert: read {:?} images, {:?} classes",
+            dataset.items.len(),
+            dataset.classes.len()
+        )
+        .as_str(),
+    );
+
+    let mut spinner = Spinner::new(
+        spinners::Hearts,
+        format!("[info]::kesa_convert: writing {:?}", &export),
+        Color::White,
+    );
+    output_format.write_dataset(&dataset, &export)?;
+    spinner.success(format!("[info]::kesa_convert: wrote {:?}", &export).as_str());
+    Ok(())
+}
@@ -0,0 +1,115 @@
+mod dedup;
+mod fileutils;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+
+use anyhow::{Error, Result};
+use clap::Parser;
+use dedup::{dedup_folder, DedupMode, DedupOptions, DEDUP_CLUSTERS_NAME};
+use fileutils::DiscoveryOptions;
+use splash::print_splash;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long, default_value_t = 4)]
+    /// max differing bits (of 64) of the perceptual hash for near duplicates,
+    /// 0 for only exact and almost identical images
+    threshold: u32,
+
+    #[arg(long, value_enum, default_value_t = DedupMode::Report)]
+    mode: DedupMode,
+
+    #[arg(long)]
+    /// where `--mode move` puts duplicates, defaults to `<folder>/duplicates`
+    duplicates: Option<String>,
+
+    #[arg(long, default_value = "dedup_cluster")]
+    /// labelme flag `--mode tag` writes the cluster to
+    flag: String,
+
+    #[arg(long)]
+    /// cluster file `--mode tag` writes, for datasets without labelme jsons,
+    /// defaults to `<folder>/dedup_clusters.yaml`
+    clusters_file: Option<String>,
+
+    #[arg(long)]
+    /// also write the report as json to this file
+    report: Option<String>,
+
+    #[command(flatten)]
+    discovery: DiscoveryOptions,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let folder = PathBuf::from(&args.folder);
+    let options = DedupOptions {
+        threshold: args.threshold,
+        mode: args.mode,
+        duplicates_folder: match &args.duplicates {
+            Some(duplicates) => PathBuf::from(duplicates),
+            None => folder.join("duplicates"),
+        },
+        flag: args.flag.to_owned(),
+        clusters_file: match &args.clusters_file {
+            Some(clusters_file) => PathBuf::from(clusters_file),
+            None => folder.join(DEDUP_CLUSTERS_NAME),
+        },
+        discovery: args.discovery.to_owned(),
+    };
+    let report = dedup_folder(&folder, &options)?;
+
+    for (idx, cluster) in report.clusters.iter().enumerate() {
+        println!(
+            "[info]::kesa_dedup: cluster {} ({}, distance <= {}): {:?}",
+            idx,
+            match cluster.exact {
+                true => "exact",
+                false => "near",
+            },
+            cluster.max_distance,
+            cluster.images
+        );
+    }
+    for image in report.unreadable.iter() {
+        println!(
+            "[warning]::kesa_dedup: {} can't be decoded, only checked for exact copies",
+            image
+        );
+    }
+    for image in report.untagged.iter() {
+        println!(
+            "[info]::kesa_dedup: {} has no labelme json to tag, it's only in {:?}",
+            image, options.clusters_file
+        );
+    }
+    if let Some(report_file) = &args.report {
+        fs::write(report_file, serde_json::to_string_pretty(&report)?)?;
+    }
+    println!(
+        "[info]::kesa_dedup: {} duplicates in {} clusters among {} images",
+        report.duplicates,
+        report.clusters.len(),
+        report.images
+    );
+    match options.mode {
+        DedupMode::Report => {}
+        DedupMode::Move => println!(
+            "[info]::kesa_dedup: moved {} images to {:?}",
+            report.moved, options.duplicates_folder
+        ),
+        DedupMode::Tag => println!(
+            "[info]::kesa_dedup: tagged {} jsons with `{}` and wrote {:?}, pass `--group-flag {}` or `--group-clusters {:?}` to kesa_l2y/kesa_split to keep clusters in one batch",
+            report.tagged, options.flag, options.clusters_file, options.flag, options.clusters_file
+        ),
+    }
+    Ok(())
+}
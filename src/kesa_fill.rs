@@ -88,7 +88,7 @@ fn create_empty_annotation_from_image(input_img: &PathBuf) -> Result<LabelmeAnno
             None, 
             _empty_shape,
             input_img.file_name().unwrap().to_string_lossy().to_string(),
-            b64img,
+            Some(b64img),
             read_img.dimensions().0 as i64,
             read_img.dimensions().1 as i64
         )
@@ -4,20 +4,34 @@ mod image_utils;
 mod label;
 mod output;
 mod splash;
+mod split;
+mod voc;
 
 use anyhow::{bail, Error, Result};
-use clap::Parser;
-use fileutils::{get_all_classes, ExportFolderOptions};
+use clap::{ArgAction, Parser};
+use fileutils::{
+    export_file, find_files, get_all_classes, read_class_names, relative_path, resolve_classes,
+    DarknetOptions, DataFormat, DiscoveryOptions, ExportFolderOptions, ExportMode,
+    UnknownClassPolicy,
+};
 use image_augmentations::augmentations::AugmentationType;
 use indicatif::ProgressBar;
-use label::read_labels_from_file;
+use label::{read_labels_from_file, LabelmeAnnotation};
 use rayon::prelude::*;
 use spinoff::{spinners, Color, Spinner};
 use splash::print_splash;
+use split::{
+    print_class_distribution, write_split_manifest, SplitArguments, SplitManifest,
+    SplitManifestEntry,
+};
 use std::collections::HashMap;
-use std::{fs, path::PathBuf};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use crate::fileutils::{get_all_classes_hash, get_all_jsons, write_data_yaml, write_yolo_to_txt};
+use crate::fileutils::{
+    get_all_classes_hash, write_dataset_description, write_yolo_seg_to_txt, write_yolo_to_txt,
+};
+use crate::voc::{get_all_voc_classes, read_voc_from_file};
 
 #[derive(Parser, Debug)]
 struct CliArguments {
@@ -29,6 +43,45 @@ struct CliArguments {
 
     #[arg(long)]
     export: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// export yolo segmentation labels (polygons)
+    /// instead of detection boxes
+    segment: bool,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// read pascal voc xmls
+    /// instead of labelme jsons
+    voc: bool,
+
+    #[arg(long, alias = "data")]
+    /// data.yaml (or a classes.txt with one name per line) of an
+    /// existing model, its names fix the class indices instead of
+    /// the sorted labels found in the folder
+    classes: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = UnknownClassPolicy::Error)]
+    /// labels missing from `--classes`
+    unknown_classes: UnknownClassPolicy,
+
+    #[arg(long, value_enum, default_value_t = DataFormat::Ultralytics)]
+    /// `ultralytics` writes a data.yaml, `darknet` writes
+    /// obj.data, obj.names and image lists
+    data_format: DataFormat,
+
+    #[command(flatten)]
+    darknet: DarknetOptions,
+
+    #[arg(long, value_enum, default_value_t = ExportMode::Copy)]
+    /// how images get into the export folder,
+    /// `move` empties the source folder
+    mode: ExportMode,
+
+    #[command(flatten)]
+    split: SplitArguments,
+
+    #[command(flatten)]
+    discovery: DiscoveryOptions,
 }
 
 fn main() -> Result<(), Error> {
@@ -48,8 +101,8 @@ fn main() -> Result<(), Error> {
         .build_global()
         .unwrap();
 
-    // TODO: put split portions
-    let export_options = ExportFolderOptions::new(export.unwrap().as_str(), 0.7)?;
+    let export_options =
+        ExportFolderOptions::for_format(export.unwrap().as_str(), args.data_format)?;
     println!("Export Options: {:#?}", &export_options);
     let mut spinner0 = Spinner::new(
         spinners::Hearts,
@@ -62,100 +115,183 @@ fn main() -> Result<(), Error> {
     let mut spinner = Spinner::new(
         spinners::Hearts,
         format!(
-            "[info]::kesa_l2y: searching for .json files in {:?}",
+            "[info]::kesa_l2y: searching for .{} files in {:?}",
+            annotation_ext(args.voc),
             &args.folder
         ),
         Color::White,
     );
-    let all_json = get_all_jsons(&args.folder)?;
-    let all_classes = get_all_classes(&all_json)?;
+    // never pick up a previous export inside the source folder
+    let mut discovery = args.discovery.to_owned();
+    discovery.skip_folders.push(export_options.export_folder.to_owned());
+    let folder = PathBuf::from(&args.folder);
+    let all_annotations = find_files(&folder, &[annotation_ext(args.voc)], &discovery)?;
+    let (all_json, found_classes) = match args.voc {
+        true => {
+            let all_xml = all_annotations;
+            let all_classes = get_all_voc_classes(&all_xml)?;
+            (all_xml, all_classes)
+        }
+        false => {
+            let all_json = all_annotations;
+            let all_classes = get_all_classes(&all_json)?;
+            (all_json, all_classes)
+        }
+    };
 
-    spinner.success(format!("[info]::kesa_l2y: found {:?} json files", &all_json.len()).as_str());
+    spinner.success(
+        format!(
+            "[info]::kesa_l2y: found {:?} .{} files",
+            &all_json.len(),
+            annotation_ext(args.voc)
+        )
+        .as_str(),
+    );
 
-    let prog = ProgressBar::new(all_json.len().to_owned() as u64);
+    let all_classes = match &args.classes {
+        Some(classes) => resolve_classes(
+            &read_class_names(Path::new(classes))?,
+            &found_classes,
+            args.unknown_classes,
+        )?,
+        None => found_classes,
+    };
     let class_hash = get_all_classes_hash(&all_classes)?;
-    println!("Starting conversion!\n");
-    all_json.par_iter().for_each(|file| {
-        prog.inc(1);
-        convert_labelme2yolo(file, &class_hash)
-    });
-    prog.finish_with_message("[info]::kesa_l2y: conversion done !\n");
-
-    // split array into 3
-    let train_split = all_json.len().to_owned() as f32 * export_options.train_ratio;
-    let val_split =
-        all_json.len().to_owned() as f32 * (export_options.train_ratio + export_options.val_ratio);
-
-    let train_batch = all_json[0..train_split as usize].to_vec();
-    let val_batch = all_json[train_split as usize..val_split as usize].to_vec();
-    let test_batch = all_json[val_split as usize..].to_vec();
-
-    write_data_yaml(&export_options, &all_classes)?;
-    move_files(train_batch, &args.folder, &export_options, "train")?;
-    move_files(val_batch, &args.folder, &export_options, "valid")?;
-    move_files(test_batch, &args.folder, &export_options, "test")?;
+
+    let all_labels = all_json
+        .par_iter()
+        .map(|file| -> Result<Vec<String>, Error> {
+            Ok(read_annotation(file)?
+                .shapes
+                .into_iter()
+                .filter(|shape| shape.shape_type.has_area())
+                .map(|shape| shape.label)
+                .collect())
+        })
+        .collect::<Result<Vec<Vec<String>>, Error>>()?;
+    let all_groups = args.split.group_keys(&folder, &all_json, |file| {
+        Ok(read_annotation(file)?.flags.unwrap_or_default())
+    })?;
+    let (batches, class_distribution) =
+        args.split.split_labeled(&all_json, &all_labels, &all_groups)?;
+    print_class_distribution(&class_distribution);
+
+    let convert_options = ConvertOptions {
+        folder,
+        class_hash,
+        segment: args.segment,
+        mode: args.mode,
+    };
+    let manifest = SplitManifest {
+        seed: args.split.seed,
+        class_distribution,
+        train: export_files(batches.train, &export_options, "train", &convert_options)?,
+        val: export_files(batches.val, &export_options, "val", &convert_options)?,
+        test: export_files(batches.test, &export_options, "test", &convert_options)?,
+    };
+    write_split_manifest(&manifest, &export_options.export_folder)?;
+    write_dataset_description(
+        &export_options,
+        &all_classes,
+        args.data_format,
+        &args.darknet,
+    )?;
     Ok(())
 }
 
-fn convert_labelme2yolo(json: &PathBuf, class_hash: &HashMap<String, i64>) -> () {
-    // de-serialize from file to struct
-    let all_shapes = read_labels_from_file(json.to_str().unwrap()).expect("read shapes error");
-    // convert to yolo txt format
-    let all_yolo = all_shapes
-        .to_yolo(&class_hash)
-        .expect("[error]::kesa_l2y: cannot convert yolo");
-    let _write = write_yolo_to_txt(all_yolo, &json);
+struct ConvertOptions {
+    /// source folder, exports mirror its subfolders
+    folder: PathBuf,
+    class_hash: HashMap<String, i64>,
+    segment: bool,
+    mode: ExportMode,
 }
 
-fn move_files(
+/// writes the yolo txt of an annotation into `label_folder`,
+/// at the same relative path as the image so yolo can pair them
+fn convert_labelme2yolo(
+    mut labelme: LabelmeAnnotation,
+    image_relative: &Path,
+    label_folder: &Path,
+    options: &ConvertOptions,
+) -> Result<(), Error> {
+    let label_path = label_folder.join(image_relative);
+    if let Some(parent) = label_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // only left with `--unknown-classes warn`
+    labelme
+        .shapes
+        .retain(|shape| options.class_hash.contains_key(&shape.label));
+    if options.segment {
+        let all_seg = labelme.to_yolo_seg(&options.class_hash)?;
+        write_yolo_seg_to_txt(all_seg, &label_path)?;
+    } else {
+        let all_yolo = labelme.to_yolo(&options.class_hash)?;
+        write_yolo_to_txt(all_yolo, &label_path)?;
+    }
+    Ok(())
+}
+
+fn annotation_ext(voc: bool) -> &'static str {
+    match voc {
+        true => "xml",
+        false => "json",
+    }
+}
+
+/// reads a labelme json, or a voc xml as labelme
+fn read_annotation(file: &Path) -> Result<LabelmeAnnotation, Error> {
+    match file.extension() {
+        Some(ext) if ext == "xml" => Ok(read_voc_from_file(file)?.to_labelme()),
+        _ => read_labels_from_file(file.to_str().unwrap()),
+    }
+}
+
+/// exports the images of a batch and writes their labels
+/// straight into the export folder, the source folder is left as is
+/// (unless `--mode move`)
+fn export_files(
     input_array: Vec<PathBuf>,
-    orig_path: &str,
     export_options: &ExportFolderOptions,
     batch: &str,
-) -> Result<(), Error> {
-    println!("[info]::kesa_l2y: moving files to `{}` batch", &batch);
+    options: &ConvertOptions,
+) -> Result<Vec<SplitManifestEntry>, Error> {
+    println!("[info]::kesa_l2y: exporting files to `{}` batch", &batch);
+    let (img_folder, label_folder) = export_options.batch_folders(batch)?;
     let prog = ProgressBar::new(input_array.len().to_owned() as u64);
-    for orig_json_file in input_array.iter() {
-        prog.inc(1);
-        let read_json_file = read_labels_from_file(orig_json_file.to_owned().to_str().unwrap())?;
-        let mut orig_txt_file = orig_json_file.to_owned();
-        orig_txt_file.set_extension("txt");
-        // use imagePath from labelme so we dont have to do some png jpeg and jpg lookup bullshit
-        let orig_image_file =
-            PathBuf::from(format!("{}/{}", &orig_path, &read_json_file.imagePath));
-        match batch {
-            "train" => {
-                let dest_image = format!(
-                    "{}/{}",
-                    &export_options.train_img, &read_json_file.imagePath
+    let manifest_entries = input_array
+        .par_iter()
+        .map(|orig_json_file| -> Result<SplitManifestEntry, Error> {
+            prog.inc(1);
+            let labelme = read_annotation(orig_json_file)?;
+            // use imagePath from labelme so we dont have to do some png jpeg and jpg lookup bullshit
+            let orig_image_file = orig_json_file
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&labelme.imagePath);
+            if orig_image_file.file_name().is_none() {
+                bail!(
+                    "[error]::kesa_l2y: {:?} has no valid imagePath",
+                    orig_json_file
                 );
-                let dest_label = PathBuf::from(&export_options.train_label)
-                    .join(orig_txt_file.file_name().to_owned().unwrap());
-                fs::rename(orig_image_file, dest_image)?;
-                fs::rename(orig_txt_file, dest_label)?;
-            }
-            "valid" => {
-                let dest_image =
-                    format!("{}/{}", &export_options.val_img, &read_json_file.imagePath);
-                let dest_label = PathBuf::from(&export_options.val_label)
-                    .join(orig_txt_file.file_name().to_owned().unwrap());
-                fs::rename(orig_image_file, dest_image)?;
-                fs::rename(orig_txt_file, dest_label)?;
-            }
-            "test" => {
-                let dest_image =
-                    format!("{}/{}", &export_options.test_img, &read_json_file.imagePath);
-                let dest_label = PathBuf::from(&export_options.test_label)
-                    .join(orig_txt_file.file_name().to_owned().unwrap());
-                fs::rename(orig_image_file, dest_image)?;
-                fs::rename(orig_txt_file, dest_label)?;
-            }
-            _ => {
-                bail!("[error]::kesa_l2y: unrecognized batch name {:?}", batch)
             }
-        }
-    }
-    prog.finish_with_message("[info]::kesa_l2y: files moved !\n");
-    println!("[info]::kesa_l2y: files moving done!");
-    Ok(())
-}
\ No newline at end of file
+            let image_relative = relative_path(&options.folder, &orig_image_file);
+            convert_labelme2yolo(labelme, &image_relative, &label_folder, options)?;
+            let dest_image = img_folder.join(&image_relative);
+            export_file(&orig_image_file, &dest_image, options.mode)?;
+            Ok(SplitManifestEntry {
+                source: orig_json_file.to_string_lossy().to_string(),
+                image: dest_image.to_string_lossy().to_string(),
+                label: label_folder
+                    .join(&image_relative)
+                    .with_extension("txt")
+                    .to_string_lossy()
+                    .to_string(),
+            })
+        })
+        .collect::<Result<Vec<SplitManifestEntry>, Error>>()?;
+    prog.finish_with_message("[info]::kesa_l2y: files exported !\n");
+    println!("[info]::kesa_l2y: files exporting done!");
+    Ok(manifest_entries)
+}
@@ -0,0 +1,95 @@
+mod class_map;
+mod coco;
+mod dataset;
+mod fileutils;
+mod formats;
+mod image_utils;
+mod label;
+mod merge;
+mod output;
+mod splash;
+mod voc;
+
+use anyhow::{Error, Result};
+use clap::{ArgAction, Parser};
+use class_map::ClassMap;
+use dataset::Dataset;
+use formats::annotation_format::{detect_format, get_format};
+use merge::{merge_datasets, source_names, MergeOptions};
+use splash::print_splash;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long, required = true, num_args = 1..)]
+    /// datasets to merge, earlier ones win on duplicates and file names
+    folders: Vec<String>,
+
+    #[arg(long)]
+    /// labelme, yolo, yolo-seg, coco or voc for every folder,
+    /// detected per folder if not supplied
+    format: Option<String>,
+
+    #[arg(long, default_value = "labelme")]
+    /// labelme, yolo, yolo-seg, coco or voc
+    output_format: String,
+
+    #[arg(long, default_value = "export")]
+    export: String,
+
+    #[arg(long)]
+    /// class mapping yaml applied to every dataset
+    map: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// keep images with the same content instead of only the first one
+    keep_duplicates: bool,
+
+    #[arg(long)]
+    /// also write the merge report as json to this file
+    report: Option<String>,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let folders: Vec<PathBuf> = args.folders.iter().map(PathBuf::from).collect();
+    let mut sources: Vec<(String, Dataset)> = vec![];
+    for (name, folder) in source_names(&folders).into_iter().zip(folders.iter()) {
+        let format_name = match &args.format {
+            Some(format_name) => format_name.to_owned(),
+            None => detect_format(folder)?.to_string(),
+        };
+        let dataset = get_format(&format_name)?.read_dataset(folder)?;
+        println!(
+            "[info]::kesa_merge: {} ({:?}): {} {} images",
+            name,
+            folder,
+            dataset.items.len(),
+            format_name
+        );
+        sources.push((name, dataset));
+    }
+    let options = MergeOptions {
+        class_map: match &args.map {
+            Some(map) => Some(ClassMap::from_file(&PathBuf::from(map))?),
+            None => None,
+        },
+        keep_duplicates: args.keep_duplicates,
+    };
+
+    let output = PathBuf::from(&args.export);
+    let (merged, report) = merge_datasets(&sources, &output, &options)?;
+    fs::create_dir_all(&output)?;
+    get_format(&args.output_format)?.write_dataset(&merged, &output)?;
+    if let Some(report_file) = &args.report {
+        fs::write(report_file, serde_json::to_string_pretty(&report)?)?;
+    }
+    println!("[info]::kesa_merge: classes: {:?}", report.classes);
+    println!(
+        "[info]::kesa_merge: wrote {} images to {:?} ({} duplicates left out, {} renamed, {} shapes dropped)",
+        report.images, output, report.duplicates, report.renamed, report.shapes_dropped
+    );
+    Ok(())
+}
@@ -0,0 +1,104 @@
+mod class_map;
+mod coco;
+mod dataset;
+mod fileutils;
+mod formats;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+mod voc;
+
+use anyhow::{bail, Error, Result};
+use clap::{ArgAction, Parser};
+use class_map::{remap_labelme_folder, remap_yolo_folder, ClassMap, RemapOptions};
+use fileutils::DiscoveryOptions;
+use formats::annotation_format::detect_format;
+use splash::print_splash;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long)]
+    /// class mapping yaml (rules, order / order_from, unmapped)
+    map: String,
+
+    #[arg(long)]
+    /// labelme, yolo or yolo-seg,
+    /// detected from the folder if not supplied
+    format: Option<String>,
+
+    #[arg(long)]
+    /// data.yaml of a yolo dataset, defaults to `<folder>/data.yaml`
+    data: Option<String>,
+
+    #[arg(long)]
+    /// write the remapped dataset here,
+    /// the labels are changed in place if not given
+    output: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// only print what would change
+    dry_run: bool,
+
+    #[arg(long)]
+    /// also write the report as json to this file
+    report: Option<String>,
+
+    #[command(flatten)]
+    discovery: DiscoveryOptions,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let folder = PathBuf::from(&args.folder);
+    let class_map = ClassMap::from_file(&PathBuf::from(&args.map))?;
+    let format_name = match &args.format {
+        Some(format_name) => format_name.to_owned(),
+        None => detect_format(&folder)?.to_string(),
+    };
+    let options = RemapOptions {
+        output: args.output.as_ref().map(PathBuf::from),
+        dry_run: args.dry_run,
+        data_yaml: args.data.as_ref().map(PathBuf::from),
+        discovery: args.discovery.to_owned(),
+    };
+    let report = match format_name.as_str() {
+        "labelme" => remap_labelme_folder(&folder, &class_map, &options)?,
+        "yolo" | "yolo-seg" => remap_yolo_folder(&folder, &class_map, &options)?,
+        other => bail!(
+            "[error]::kesa_remap: {} datasets cannot be remapped, convert them to labelme or yolo first",
+            other
+        ),
+    };
+
+    for (from, to) in report.mapping.iter() {
+        match to {
+            Some(to) if to == from => {}
+            Some(to) => println!("[info]::kesa_remap: {} -> {}", from, to),
+            None => println!("[info]::kesa_remap: {} -> (dropped)", from),
+        }
+    }
+    println!("[info]::kesa_remap: classes: {:?}", report.classes);
+    if let Some(report_file) = &args.report {
+        fs::write(report_file, serde_json::to_string_pretty(&report)?)?;
+    }
+    println!(
+        "[info]::kesa_remap: {} {} of {} {} files ({} shapes renamed, {} dropped)",
+        match args.dry_run {
+            true => "would change",
+            false => "changed",
+        },
+        report.files_changed,
+        report.files_checked,
+        format_name,
+        report.shapes_renamed,
+        report.shapes_dropped
+    );
+    Ok(())
+}
@@ -0,0 +1,90 @@
+mod fileutils;
+mod image_utils;
+mod label;
+mod output;
+mod repair;
+mod splash;
+
+use anyhow::{Error, Result};
+use clap::{ArgAction, Parser};
+use fileutils::DiscoveryOptions;
+use repair::{repair_folder, RepairFix, RepairOptions};
+use splash::print_splash;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long)]
+    /// write the repaired dataset (jsons and images) here,
+    /// the jsons are fixed in place if not given
+    output: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// only print what would change
+    dry_run: bool,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = RepairFix::all())]
+    /// fixes to apply, comma separated
+    fixes: Vec<RepairFix>,
+
+    #[arg(long)]
+    /// also write the changes as json to this file
+    report: Option<String>,
+
+    #[command(flatten)]
+    discovery: DiscoveryOptions,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let options = RepairOptions {
+        fixes: args.fixes.to_owned(),
+        dry_run: args.dry_run,
+        output: args.output.as_ref().map(PathBuf::from),
+        discovery: args.discovery.to_owned(),
+    };
+    let report = repair_folder(&PathBuf::from(&args.folder), &options)?;
+
+    for change in report.changes.iter() {
+        let shape = match change.shape {
+            Some(shape) => format!(" [{}]", shape),
+            None => String::new(),
+        };
+        println!(
+            "[info]::kesa_repair: {}{}: {:?}\n  - {}\n  + {}",
+            change.file, shape, change.fix, change.before, change.after
+        );
+    }
+    for skip in report.skipped.iter() {
+        println!(
+            "[error]::kesa_repair: {}: cannot repair, {}",
+            skip.file, skip.reason
+        );
+    }
+    for problem in report.unfixed.iter() {
+        println!(
+            "[warning]::kesa_repair: {}: {}, the other fixes were applied",
+            problem.file, problem.reason
+        );
+    }
+    if let Some(report_file) = &args.report {
+        fs::write(report_file, serde_json::to_string_pretty(&report)?)?;
+    }
+    println!(
+        "[info]::kesa_repair: {} {} of {} files ({} changes, {} skipped)",
+        match args.dry_run {
+            true => "would change",
+            false => "changed",
+        },
+        report.files_changed,
+        report.files_checked,
+        report.changes.len(),
+        report.skipped.len()
+    );
+    Ok(())
+}
@@ -4,10 +4,16 @@ mod image_utils;
 mod label;
 mod output;
 mod splash;
+mod split;
+mod voc;
 
 use anyhow::{bail, Error, Result};
-use clap::Parser;
-use fileutils::{get_all_classes, ExportFolderOptions};
+use clap::{ArgAction, Parser};
+use fileutils::{
+    export_file, find_files, find_image_for, get_all_classes, read_class_names, relative_path,
+    resolve_classes, DarknetOptions, DataFormat, DiscoveryOptions, ExportFolderOptions,
+    ExportMode, UnknownClassPolicy,
+};
 use image_augmentations::augmentations::AugmentationType;
 use indicatif::ProgressBar;
 use kesa::fileutils::get_all_images;
@@ -15,12 +21,15 @@ use label::read_labels_from_file;
 use rayon::prelude::*;
 use spinoff::{spinners, Color, Spinner};
 use splash::print_splash;
+use split::{
+    print_class_distribution, write_split_manifest, SplitArguments, SplitManifest,
+    SplitManifestEntry,
+};
 use std::collections::HashMap;
-use std::{fs, path::PathBuf};
+use std::path::{Path, PathBuf};
 
-use crate::fileutils::{
-    get_all_classes_hash, get_all_jsons, get_all_txts, write_data_yaml, write_yolo_to_txt,
-};
+use crate::fileutils::{read_yolo_from_txt, write_dataset_description};
+use crate::voc::{get_all_voc_classes, read_voc_from_file};
 
 #[derive(Parser, Debug)]
 struct CliArguments {
@@ -34,7 +43,43 @@ struct CliArguments {
     export: Option<String>,
 
     #[arg(long)]
-    ext: String,
+    /// image extension, looks for any known
+    /// image format next to the label if not given
+    ext: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// split pascal voc xmls
+    /// instead of yolo txts
+    voc: bool,
+
+    #[arg(long)]
+    /// data.yaml (or a classes.txt with one name per line) the
+    /// labels were made with, written to the export's data.yaml
+    /// instead of the sorted labels of the labelme jsons
+    classes: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = UnknownClassPolicy::Error)]
+    /// labels missing from `--classes`
+    unknown_classes: UnknownClassPolicy,
+
+    #[arg(long, value_enum, default_value_t = DataFormat::Ultralytics)]
+    /// `ultralytics` writes a data.yaml, `darknet` writes
+    /// obj.data, obj.names and image lists
+    data_format: DataFormat,
+
+    #[command(flatten)]
+    darknet: DarknetOptions,
+
+    #[arg(long, value_enum, default_value_t = ExportMode::Copy)]
+    /// how files get into the export folder,
+    /// `move` empties the source folder
+    mode: ExportMode,
+
+    #[command(flatten)]
+    split: SplitArguments,
+
+    #[command(flatten)]
+    discovery: DiscoveryOptions,
 }
 
 fn main() -> Result<(), Error> {
@@ -54,82 +99,158 @@ fn main() -> Result<(), Error> {
         .num_threads(workers.unwrap().try_into().unwrap())
         .build_global()
         .unwrap();
-    let export_options = ExportFolderOptions::new(export.unwrap().as_str(), 0.7)?;
+    let export_options =
+        ExportFolderOptions::for_format(export.unwrap().as_str(), args.data_format)?;
     export_options.create_folders()?;
 
-    let all_txt = get_all_txts(&args.folder)?;
-
-    let all_json = get_all_jsons(&args.folder)?;
-    let all_classes = get_all_classes(&all_json)?;
-
-    let train_split = all_txt.len().to_owned() as f32 * export_options.train_ratio;
-    let val_split =
-        all_txt.len().to_owned() as f32 * (export_options.train_ratio + export_options.val_ratio);
+    // never pick up a previous export inside the source folder
+    let mut discovery = args.discovery.to_owned();
+    discovery.skip_folders.push(export_options.export_folder.to_owned());
+    let folder = PathBuf::from(&args.folder);
+    let (all_txt, found_classes) = match args.voc {
+        true => {
+            let all_xml = find_files(&folder, &["xml"], &discovery)?;
+            let all_classes = get_all_voc_classes(&all_xml)?;
+            (all_xml, all_classes)
+        }
+        false => {
+            let all_json = find_files(&folder, &["json"], &discovery)?;
+            (
+                find_files(&folder, &["txt"], &discovery)?,
+                get_all_classes(&all_json)?,
+            )
+        }
+    };
 
-    let train_batch = all_txt[0..train_split as usize].to_vec();
-    let val_batch = all_txt[train_split as usize..val_split as usize].to_vec();
-    let test_batch = all_txt[val_split as usize..].to_vec();
+    let all_classes = match &args.classes {
+        Some(classes) => resolve_classes(
+            &read_class_names(Path::new(classes))?,
+            &found_classes,
+            args.unknown_classes,
+        )?,
+        None => found_classes,
+    };
 
-    write_data_yaml(&export_options, &all_classes)?;
+    let all_labels = all_txt
+        .iter()
+        .map(|file| read_file_labels(file, &all_classes, args.voc))
+        .collect::<Result<Vec<Vec<String>>, Error>>()?;
+    // txts are exported as they are, ids past the class list can't be fixed here
+    if args.classes.is_some() && !args.voc {
+        let mut unknown_ids: Vec<&String> = all_labels
+            .iter()
+            .flatten()
+            .filter(|label| !all_classes.contains(label))
+            .collect();
+        unknown_ids.sort();
+        unknown_ids.dedup();
+        match (unknown_ids.is_empty(), args.unknown_classes) {
+            (true, _) => {}
+            (false, UnknownClassPolicy::Warn) => println!(
+                "[warning]::kesa_split: class ids {:?} are not in the class list",
+                unknown_ids
+            ),
+            (false, _) => bail!(
+                "[error]::kesa_split: class ids {:?} are not in the class list",
+                unknown_ids
+            ),
+        }
+    }
+    // flags come from the labelme json next to the label, if there is one
+    let all_groups = args.split.group_keys(&folder, &all_txt, |file| {
+        let json = file.with_extension("json");
+        match json.exists() {
+            true => Ok(read_labels_from_file(json.to_str().unwrap())?
+                .flags
+                .unwrap_or_default()),
+            false => Ok(HashMap::new()),
+        }
+    })?;
+    let (batches, class_distribution) =
+        args.split.split_labeled(&all_txt, &all_labels, &all_groups)?;
+    print_class_distribution(&class_distribution);
 
-    move_txt_files(
-        train_batch,
-        &args.folder,
+    let export_batch = |batch: Vec<PathBuf>, name: &str| {
+        export_txt_files(
+            batch,
+            &folder,
+            &export_options,
+            name,
+            args.ext.as_deref(),
+            args.mode,
+        )
+    };
+    let manifest = SplitManifest {
+        seed: args.split.seed,
+        class_distribution,
+        train: export_batch(batches.train, "train")?,
+        val: export_batch(batches.val, "val")?,
+        test: export_batch(batches.test, "test")?,
+    };
+    write_split_manifest(&manifest, &export_options.export_folder)?;
+    write_dataset_description(
         &export_options,
-        "train",
-        &args.ext,
+        &all_classes,
+        args.data_format,
+        &args.darknet,
     )?;
-    move_txt_files(val_batch, &args.folder, &export_options, "valid", &args.ext)?;
-    move_txt_files(test_batch, &args.folder, &export_options, "test", &args.ext)?;
     Ok(())
 }
 
-fn move_txt_files(
+/// class names of every object in a yolo txt (or voc xml),
+/// ids without a name in the class list are kept as the id
+fn read_file_labels(file: &Path, all_classes: &[String], voc: bool) -> Result<Vec<String>, Error> {
+    if voc {
+        return Ok(read_voc_from_file(file)?
+            .objects
+            .into_iter()
+            .map(|object| object.name)
+            .collect());
+    }
+    Ok(read_yolo_from_txt(file)?
+        .iter()
+        .map(|yolo| match all_classes.get(yolo.class() as usize) {
+            Some(name) => name.to_owned(),
+            None => yolo.class().to_string(),
+        })
+        .collect())
+}
+
+/// exports labels and their images into a batch, mirroring
+/// the subfolders of `orig_path`. the source folder is left
+/// as is (unless `--mode move`)
+fn export_txt_files(
     input_txt_array: Vec<PathBuf>,
-    orig_path: &str,
+    orig_path: &Path,
     export_options: &ExportFolderOptions,
     batch: &str,
-    img_ext: &str, // will change this later to match or something idk
-) -> Result<(), Error> {
-    println!("moving files  to `{}` batch", &batch);
+    img_ext: Option<&str>,
+    mode: ExportMode,
+) -> Result<Vec<SplitManifestEntry>, Error> {
+    println!("exporting files to `{}` batch", &batch);
+    let (img_folder, label_folder) = export_options.batch_folders(batch)?;
     let prog = ProgressBar::new(input_txt_array.len().to_owned() as u64);
+    let mut manifest_entries: Vec<SplitManifestEntry> = vec![];
     for orig_txt_file in input_txt_array.iter() {
         prog.inc(1);
-        let orig_txt_file = orig_txt_file.to_owned();
-        let mut orig_img_file = orig_txt_file.clone();
-        orig_img_file.set_extension(img_ext);
-
-        match batch {
-            "train" => {
-                let dest_image = PathBuf::from(&export_options.train_img)
-                    .join(orig_img_file.file_name().to_owned().unwrap());
-                let dest_label = PathBuf::from(&export_options.train_label)
-                    .join(orig_txt_file.file_name().to_owned().unwrap());
-                fs::rename(orig_img_file, dest_image)?;
-                fs::rename(orig_txt_file, dest_label)?;
-            }
-            "valid" => {
-                let dest_image = PathBuf::from(&export_options.val_img)
-                    .join(orig_img_file.file_name().to_owned().unwrap());
-                let dest_label = PathBuf::from(&export_options.val_label)
-                    .join(orig_txt_file.file_name().to_owned().unwrap());
-                fs::rename(orig_img_file, dest_image)?;
-                fs::rename(orig_txt_file, dest_label)?;
-            }
-            "test" => {
-                let dest_image = PathBuf::from(&export_options.test_img)
-                    .join(orig_img_file.file_name().to_owned().unwrap());
-                let dest_label = PathBuf::from(&export_options.test_label)
-                    .join(orig_txt_file.file_name().to_owned().unwrap());
-                fs::rename(orig_img_file, dest_image)?;
-                fs::rename(orig_txt_file, dest_label)?;
-            }
-            _ => {
-                bail!("unrecognized batch name {:?}", batch)
-            }
-        }
+        let orig_img_file = match img_ext {
+            Some(ext) => orig_txt_file.with_extension(ext),
+            None => match find_image_for(orig_txt_file) {
+                Some(image) => image,
+                None => bail!("[error]::kesa_split: no image found for {:?}", orig_txt_file),
+            },
+        };
+        let dest_image = img_folder.join(relative_path(orig_path, &orig_img_file));
+        let dest_label = label_folder.join(relative_path(orig_path, orig_txt_file));
+        export_file(&orig_img_file, &dest_image, mode)?;
+        export_file(orig_txt_file, &dest_label, mode)?;
+        manifest_entries.push(SplitManifestEntry {
+            source: orig_txt_file.to_string_lossy().to_string(),
+            image: dest_image.to_string_lossy().to_string(),
+            label: dest_label.to_string_lossy().to_string(),
+        });
     }
-    prog.finish_with_message("[info]::kesa_split: files moved !\n");
-    println!("[info]::kesa_split: files moving done!");
-    Ok(())
+    prog.finish_with_message("[info]::kesa_split: files exported !\n");
+    println!("[info]::kesa_split: files exporting done!");
+    Ok(manifest_entries)
 }
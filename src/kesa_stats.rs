@@ -0,0 +1,168 @@
+mod coco;
+mod dataset;
+mod fileutils;
+mod formats;
+mod image_utils;
+mod label;
+mod output;
+mod plotting;
+mod splash;
+mod stats;
+mod voc;
+
+use anyhow::{Error, Result};
+use clap::Parser;
+use formats::annotation_format::{detect_format, get_format};
+use plotting::{draw_bar_chart, draw_heatmap, ChartFormat};
+use splash::print_splash;
+use stats::{compute_stats, write_stats_to_json, DatasetStats};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// only the most common resolutions get a bar
+const MAX_RESOLUTION_BARS: usize = 20;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long)]
+    /// labelme, yolo, yolo-seg, coco or voc,
+    /// detected from the folder if not supplied
+    format: Option<String>,
+
+    #[arg(long, default_value = "stats")]
+    /// folder for the charts and `stats.json`
+    output: String,
+
+    #[arg(long, value_enum, default_value_t = ChartFormat::Png)]
+    chart_format: ChartFormat,
+}
+
+fn draw_charts(
+    stats: &DatasetStats,
+    output: &Path,
+    chart_format: ChartFormat,
+) -> Result<(), Error> {
+    let chart = |name: &str| output.join(format!("{}.{}", name, chart_format.extension()));
+    let classes: Vec<String> = stats.classes.keys().cloned().collect();
+    draw_bar_chart(
+        &chart("class_instances"),
+        "instances per class",
+        &classes,
+        &stats
+            .classes
+            .values()
+            .map(|c| c.instances)
+            .collect::<Vec<usize>>(),
+    )?;
+    draw_bar_chart(
+        &chart("class_images"),
+        "images per class",
+        &classes,
+        &stats
+            .classes
+            .values()
+            .map(|c| c.images)
+            .collect::<Vec<usize>>(),
+    )?;
+    draw_bar_chart(
+        &chart("objects_per_image"),
+        "objects per image",
+        &stats
+            .objects_per_image
+            .keys()
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>(),
+        &stats
+            .objects_per_image
+            .values()
+            .copied()
+            .collect::<Vec<usize>>(),
+    )?;
+
+    let mut resolutions: Vec<(&String, &usize)> = stats.resolutions.iter().collect();
+    resolutions.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    resolutions.truncate(MAX_RESOLUTION_BARS);
+    draw_bar_chart(
+        &chart("resolutions"),
+        "image resolutions",
+        &resolutions
+            .iter()
+            .map(|(r, _)| r.to_string())
+            .collect::<Vec<String>>(),
+        &resolutions.iter().map(|(_, n)| **n).collect::<Vec<usize>>(),
+    )?;
+
+    for (name, title, bins) in [
+        ("box_width", "box width / image width", &stats.box_width),
+        ("box_height", "box height / image height", &stats.box_height),
+        (
+            "aspect_ratio",
+            "box aspect ratio (w / h)",
+            &stats.aspect_ratio,
+        ),
+    ] {
+        draw_bar_chart(&chart(name), title, &bins.bin_names(), &bins.counts)?;
+    }
+    draw_heatmap(&chart("box_centers"), "box centers", &stats.center_heatmap)?;
+
+    for (split, split_stats) in stats.splits.iter() {
+        let split_classes: Vec<String> = split_stats.classes.keys().cloned().collect();
+        draw_bar_chart(
+            &chart(&format!("class_instances_{}", split)),
+            &format!("instances per class ({})", split),
+            &split_classes,
+            &split_stats
+                .classes
+                .values()
+                .map(|c| c.instances)
+                .collect::<Vec<usize>>(),
+        )?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let folder = PathBuf::from(&args.folder);
+    let format_name = match &args.format {
+        Some(format_name) => format_name.to_owned(),
+        None => {
+            let detected = detect_format(&folder)?;
+            println!("[info]::kesa_stats: detected {} dataset", detected);
+            detected.to_string()
+        }
+    };
+    let dataset = get_format(&format_name)?.read_dataset(&folder)?;
+    let stats = compute_stats(&dataset)?;
+
+    let output = PathBuf::from(&args.output);
+    fs::create_dir_all(&output)?;
+    write_stats_to_json(&stats, &output.join("stats.json"))?;
+    draw_charts(&stats, &output, args.chart_format)?;
+
+    println!(
+        "[info]::kesa_stats: {} images, {} instances, {} without labels",
+        stats.images, stats.instances, stats.images_without_labels
+    );
+    for (class, class_stats) in stats.classes.iter() {
+        println!(
+            "[info]::kesa_stats:   {}: {} instances in {} images",
+            class, class_stats.instances, class_stats.images
+        );
+    }
+    for (split, split_stats) in stats.splits.iter() {
+        println!(
+            "[info]::kesa_stats: {}: {} images, {} instances",
+            split, split_stats.images, split_stats.instances
+        );
+    }
+    println!(
+        "[info]::kesa_stats: wrote charts and stats.json to {:?}",
+        output
+    );
+    Ok(())
+}
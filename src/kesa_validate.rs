@@ -0,0 +1,102 @@
+mod fileutils;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+mod validate;
+
+use anyhow::{Error, Result};
+use clap::{ArgAction, Parser};
+use fileutils::{read_data_yaml, DiscoveryOptions};
+use splash::print_splash;
+use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
+use validate::{validate_folder, Severity, ValidateOptions};
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long)]
+    /// data.yaml with the known class names,
+    /// `<folder>/data.yaml` is used if it exists
+    data: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// print the report as json instead of text
+    json: bool,
+
+    #[arg(long)]
+    /// also write the json report to this file
+    output: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// fail on warnings too
+    strict: bool,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// don't open the images to check their size
+    skip_image_size: bool,
+
+    #[command(flatten)]
+    discovery: DiscoveryOptions,
+}
+
+/// exits with 1 if there are errors (or warnings with `--strict`),
+/// so it can gate a ci pipeline
+fn main() -> Result<(), Error> {
+    let args = CliArguments::parse();
+    if !args.json {
+        print_splash();
+    }
+    let folder = PathBuf::from(&args.folder);
+    let data_yaml = match &args.data {
+        Some(data) => Some(PathBuf::from(data)),
+        None => Some(folder.join("data.yaml")).filter(|data| data.exists()),
+    };
+    let classes = match &data_yaml {
+        Some(data_yaml) => Some(read_data_yaml(data_yaml)?.names),
+        None => None,
+    };
+    let options = ValidateOptions {
+        classes,
+        check_image_size: !args.skip_image_size,
+        discovery: args.discovery.to_owned(),
+    };
+
+    let report = validate_folder(&folder, &options)?;
+    if let Some(output) = &args.output {
+        fs::write(output, serde_json::to_string_pretty(&report)?)?;
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for issue in report.issues.iter() {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let shape = match issue.shape {
+                Some(shape) => format!(" [{}]", shape),
+                None => String::new(),
+            };
+            println!(
+                "[{}]::kesa_validate: {}{}: {:?}: {}",
+                severity, issue.file, shape, issue.kind, issue.message
+            );
+        }
+        println!(
+            "[info]::kesa_validate: checked {} label files and {} images, {} errors, {} warnings",
+            report.labels_checked, report.images_checked, report.errors, report.warnings
+        );
+        for (kind, count) in report.counts.iter() {
+            println!("[info]::kesa_validate:   {:?}: {}", kind, count);
+        }
+    }
+    if report.failed(args.strict) {
+        exit(1);
+    }
+    Ok(())
+}
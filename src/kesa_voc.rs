@@ -0,0 +1,80 @@
+mod fileutils;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+mod voc;
+
+use anyhow::{Error, Result};
+use clap::{ArgAction, Parser};
+use fileutils::{get_all_jsons, get_all_xmls, write_labelme_to_json};
+use indicatif::ProgressBar;
+use label::read_labels_from_file;
+use rayon::prelude::*;
+use splash::print_splash;
+use std::path::Path;
+use voc::{read_voc_from_file, write_voc_to_xml, VocAnnotation};
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    folder: String,
+
+    #[arg(long)]
+    workers: Option<i64>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// convert voc xmls to labelme jsons,
+    /// by default labelme jsons are converted to voc xmls
+    to_labelme: bool,
+}
+
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let workers = args.workers.unwrap_or(4);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(workers.try_into().unwrap())
+        .build_global()
+        .unwrap();
+
+    let all_files = match args.to_labelme {
+        true => get_all_xmls(&args.folder)?,
+        false => get_all_jsons(&args.folder)?,
+    };
+    println!(
+        "[info]::kesa_voc: converting {:?} files in {:?}",
+        all_files.len(),
+        &args.folder
+    );
+    let prog = ProgressBar::new(all_files.len() as u64);
+    all_files.par_iter().for_each(|file| {
+        prog.inc(1);
+        let converted = match args.to_labelme {
+            true => voc2labelme(file),
+            false => labelme2voc(file),
+        };
+        if let Err(e) = converted {
+            eprintln!(
+                "[error]::kesa_voc: cannot convert {:?}\nError: {:?}",
+                file, e
+            );
+        }
+    });
+    prog.finish_with_message("[info]::kesa_voc: conversion done !\n");
+    Ok(())
+}
+
+/// output is written next to the annotation,
+/// named after the image it belongs to
+fn voc2labelme(xml: &Path) -> Result<(), Error> {
+    let labelme = read_voc_from_file(xml)?.to_labelme();
+    write_labelme_to_json(&labelme, &xml.with_file_name(&labelme.imagePath))
+}
+
+fn labelme2voc(json: &Path) -> Result<(), Error> {
+    let labelme = read_labels_from_file(json.to_str().unwrap())?;
+    let voc = VocAnnotation::from_labelme(&labelme)?;
+    write_voc_to_xml(&voc, &json.with_file_name(&labelme.imagePath))
+}
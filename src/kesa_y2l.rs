@@ -0,0 +1,85 @@
+mod coco;
+mod dataset;
+mod fileutils;
+mod formats;
+mod image_utils;
+mod label;
+mod output;
+mod splash;
+mod voc;
+
+use anyhow::{Error, Result};
+use clap::{ArgAction, Parser};
+use formats::annotation_format::AnnotationFormat;
+use formats::labelme_format::LabelmeFormat;
+use formats::yolo_format::YoloFormat;
+use spinoff::{spinners, Color, Spinner};
+use splash::print_splash;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+struct CliArguments {
+    #[arg(long)]
+    /// yolo export folder (`train`/`val`/`test` with
+    /// `images` and `labels`), or a flat folder
+    /// with images and txts side by side
+    folder: String,
+
+    #[arg(long)]
+    /// data.yaml with the class names,
+    /// defaults to `<folder>/data.yaml`
+    data: Option<String>,
+
+    #[arg(long)]
+    workers: Option<i64>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// embed the image into the labelme jsons (`imageData`)
+    image_data: bool,
+}
+
+/// labelme jsons are written next to the images,
+/// images without a txt get an empty annotation
+/// so they can still be reviewed
+fn main() -> Result<(), Error> {
+    print_splash();
+    let args = CliArguments::parse();
+    let workers = args.workers.unwrap_or(4);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(workers.try_into().unwrap())
+        .build_global()
+        .unwrap();
+
+    let folder = PathBuf::from(&args.folder);
+    let yolo = YoloFormat {
+        segment: false,
+        data_yaml: args.data.as_ref().map(PathBuf::from),
+    };
+    let mut spinner = Spinner::new(
+        spinners::Hearts,
+        format!("[info]::kesa_y2l: reading yolo dataset {:?}", &args.folder),
+        Color::White,
+    );
+    let dataset = yolo.read_dataset(&folder)?;
+    spinner.success(
+        format!(
+            "[info]::kesa_y2l: found {:?} images, {:?} classes",
+            dataset.items.len(),
+            dataset.classes.len()
+        )
+        .as_str(),
+    );
+
+    let mut spinner = Spinner::new(
+        spinners::Hearts,
+        "[info]::kesa_y2l: writing labelme jsons",
+        Color::White,
+    );
+    let labelme = LabelmeFormat {
+        image_data: args.image_data,
+    };
+    labelme.write_dataset(&dataset, &folder)?;
+    spinner.success("[info]::kesa_y2l: conversion done !");
+    Ok(())
+}
@@ -86,8 +86,9 @@ impl YoloBbox {
                     vec![self.xyxy.x2, self.xyxy.y2],
                 ],
                 group_id: Some(self.confidence.to_string()),
-                shape_type: String::from("rectangle"),
+                shape_type: ShapeType::Rectangle,
                 flags: Some(HashMap::new()),
+                extra: serde_json::Map::new(),
             }),
             CoordinateType::Normalized => {
                 bail!("[error]::YoloBBox: please convert coordinate type to screen first ! (using YoloBBox::to_screen)")
@@ -180,7 +181,7 @@ impl Xyxy {
 }
 
 /// yolo txt export format
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct YoloAnnotation {
     pub class: i64,
     pub xmin: f32,
@@ -220,49 +221,20 @@ impl YoloAnnotation {
     }
 }
 
-impl OutputFormat for YoloAnnotation {
-    fn to_yolo_vec(&self) -> Result<Vec<YoloAnnotation>, anyhow::Error> {
-        todo!()
-    }
-    fn to_yolo(&self) -> Result<YoloAnnotation, anyhow::Error> {
-        // this might end very badly
-        panic!("Invalid Operation , cannot convert `YoloAnnotation` to `YoloAnnotation`")
-    }
-    fn to_shape(
-        &self,
-        all_classes: &Vec<String>,
-        original_dimension: &(u32, u32),
-        inference_dimension: &(u32, u32),
-    ) -> Result<Vec<Shape>, anyhow::Error> {
-        Ok(vec![Shape {
-            label: all_classes[self.class as usize].to_owned(),
-            points: vec![vec![self.xmin, self.ymin], vec![self.w, self.h]],
-            group_id: Some(self.confidence.to_string()),
-            shape_type: String::from("rectangle"),
-            flags: Some(HashMap::new()),
-        }])
-    }
-    fn to_labelme(
-        &self,
-        all_classes: &Vec<String>,
-        original_dimension: &(u32, u32),
-        filename: &str,
-        image_file: &DynamicImage,
-        inference_dimension: &(u32, u32),
-    ) -> Result<LabelmeAnnotation, anyhow::Error> {
-        todo!()
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LabelmeAnnotation {
     pub version: String,
     pub flags: Option<HashMap<String, String>>,
     pub shapes: Vec<Shape>,
     pub imagePath: String,
-    pub imageData: String,
+    /// base64 image, `null` when labelme was told
+    /// not to store the image inside the json
+    pub imageData: Option<String>,
     pub imageWidth: i64,
     pub imageHeight: i64,
+    /// keys kesa doesn't model, written back untouched
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl LabelmeAnnotation {
@@ -270,12 +242,13 @@ impl LabelmeAnnotation {
     pub fn new(flags: Option<HashMap<String, String>>, 
                 shapes: Vec<Shape>,
                 image_path: String,
-                image_data: String,
+                image_data: Option<String>,
                 image_width: i64,
                 image_height: i64) -> LabelmeAnnotation {
         LabelmeAnnotation {
             version: String::from("5.1.1"),
             flags: flags,
+            extra: serde_json::Map::new(),
             shapes: shapes,
             imagePath: image_path,
             imageWidth: image_width,
@@ -285,16 +258,12 @@ impl LabelmeAnnotation {
     }
 
 
+    /// tight bounding boxes of every shape,
+    /// polygons and circles included
     pub fn get_xyxy(&self) -> Result<Vec<Xyxy>, Error> {
         let mut all_xyxys: Vec<Xyxy> = vec![];
         for shape in self.shapes.iter() {
-            all_xyxys.push(Xyxy::new(
-                CoordinateType::Screen,
-                shape.points[0][0],
-                shape.points[0][1],
-                shape.points[1][0],
-                shape.points[1][1],
-            ));
+            all_xyxys.push(shape.bbox()?);
         }
         Ok(all_xyxys)
     }
@@ -307,10 +276,11 @@ impl LabelmeAnnotation {
         Ok(LabelmeAnnotation { 
             version,
             flags: Some(flags),
+            extra: serde_json::Map::new(),
             shapes: shapes.to_owned(),
             imageWidth: image_file.dimensions().0.to_owned() as i64,
             imageHeight: image_file.dimensions().1.to_owned() as i64,
-            imageData: base64img,
+            imageData: Some(base64img),
             imagePath: _file.file_name().unwrap().to_string_lossy().to_string()
         }) 
     }
@@ -321,15 +291,20 @@ impl LabelmeAnnotation {
         todo!()
     }
 
-    /// converts labelme annotation to yolo shape
+    /// converts labelme annotation to yolo shape,
+    /// polygons and circles are reduced to their tight bbox.
+    /// points, lines and linestrips have no area so they are skipped
     pub fn to_yolo(&self, class_hash: &HashMap<String, i64>) -> Result<Vec<YoloAnnotation>, Error> {
         let mut yolo_label_list: Vec<YoloAnnotation> = vec![];
         for shape in self.shapes.iter() {
-            let temp_xyxy: Xyxy = get_xyxy_from_shape(&shape, CoordinateType::Normalized);
-            let x = (((temp_xyxy.x1 + temp_xyxy.x2) / 2.0) / self.imageWidth as f32).abs();
-            let y = (((temp_xyxy.y1 + temp_xyxy.y2) / 2.0) / self.imageHeight as f32).abs();
-            let w = ((temp_xyxy.x2 - temp_xyxy.x1) / self.imageWidth as f32).abs();
-            let h = ((temp_xyxy.y2 - temp_xyxy.y1) / self.imageHeight as f32).abs();
+            if skip_unsupported(shape) || !shape.shape_type.has_area() {
+                continue;
+            }
+            let temp_xyxy: Xyxy = get_xyxy_from_shape(shape, CoordinateType::Screen)?;
+            let x = ((temp_xyxy.x1 + temp_xyxy.x2) / 2.0) / self.imageWidth as f32;
+            let y = ((temp_xyxy.y1 + temp_xyxy.y2) / 2.0) / self.imageHeight as f32;
+            let w = (temp_xyxy.x2 - temp_xyxy.x1) / self.imageWidth as f32;
+            let h = (temp_xyxy.y2 - temp_xyxy.y1) / self.imageHeight as f32;
             let label_index = class_hash.get(&shape.label).expect("cannot find index!");
             let yolo_struct: YoloAnnotation = YoloAnnotation {
                 class: *label_index, // deref bih, uh
@@ -344,6 +319,132 @@ impl LabelmeAnnotation {
         }
         Ok(yolo_label_list)
     }
+
+    /// converts labelme annotation to yolo segmentation lines,
+    /// rectangles and circles are turned into polygons first
+    /// and every vertex is normalized by the image size
+    pub fn to_yolo_seg(
+        &self,
+        class_hash: &HashMap<String, i64>,
+    ) -> Result<Vec<YoloSegAnnotation>, Error> {
+        let mut seg_list: Vec<YoloSegAnnotation> = vec![];
+        for shape in self.shapes.iter() {
+            if skip_unsupported(shape) || !shape.shape_type.has_area() {
+                continue;
+            }
+            let label_index = match class_hash.get(&shape.label) {
+                Some(idx) => *idx,
+                None => bail!("[error]::label: cannot find index for {:?}", &shape.label),
+            };
+            let points: Vec<Vec<f32>> = shape
+                .polygon()?
+                .iter()
+                .map(|p| {
+                    vec![
+                        (p[0] / self.imageWidth as f32).clamp(0.0, 1.0),
+                        (p[1] / self.imageHeight as f32).clamp(0.0, 1.0),
+                    ]
+                })
+                .collect();
+            seg_list.push(YoloSegAnnotation {
+                class: label_index,
+                points,
+            });
+        }
+        Ok(seg_list)
+    }
+}
+
+/// one parsed line of a yolo txt,
+/// `class x y w h [confidence]` for boxes and
+/// `class x1 y1 ... xn yn` (at least 3 vertices) for segments
+#[derive(Debug, Clone, PartialEq)]
+pub enum YoloLabel {
+    Bbox(YoloAnnotation),
+    Segment(YoloSegAnnotation),
+}
+
+impl YoloLabel {
+    pub fn from_line(line: &str) -> Result<YoloLabel, Error> {
+        let values: Vec<f32> = line
+            .split_whitespace()
+            .map(|v| v.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()?;
+        let class = match values.first() {
+            Some(class) => *class as i64,
+            None => bail!("[error]::label: empty yolo line"),
+        };
+        match values.len() {
+            5 | 6 => Ok(YoloLabel::Bbox(YoloAnnotation::new(
+                class,
+                values[1],
+                values[2],
+                values[3],
+                values[4],
+                values.get(5).copied().unwrap_or(1.0),
+            ))),
+            n if n >= 7 && n % 2 == 1 => Ok(YoloLabel::Segment(YoloSegAnnotation {
+                class,
+                points: values[1..].chunks_exact(2).map(|p| p.to_vec()).collect(),
+            })),
+            n => bail!("[error]::label: invalid yolo line with {} values {:?}", n, line),
+        }
+    }
+
+    pub fn class(&self) -> i64 {
+        match self {
+            YoloLabel::Bbox(bbox) => bbox.class,
+            YoloLabel::Segment(segment) => segment.class,
+        }
+    }
+
+    /// denormalizes the label into a labelme shape,
+    /// img_dims is (w, h)
+    pub fn to_shape(&self, all_classes: &[String], img_dims: &(u32, u32)) -> Result<Shape, Error> {
+        let label = match all_classes.get(self.class() as usize) {
+            Some(label) => label.to_owned(),
+            None => bail!(
+                "[error]::label: class index {} is out of range for {} classes",
+                self.class(),
+                all_classes.len()
+            ),
+        };
+        let (w, h) = (img_dims.0 as f32, img_dims.1 as f32);
+        let (points, shape_type) = match self {
+            // yolo boxes are stored as center x, center y, w, h
+            YoloLabel::Bbox(bbox) => (
+                vec![
+                    vec![(bbox.xmin - bbox.w / 2.0) * w, (bbox.ymin - bbox.h / 2.0) * h],
+                    vec![(bbox.xmin + bbox.w / 2.0) * w, (bbox.ymin + bbox.h / 2.0) * h],
+                ],
+                ShapeType::Rectangle,
+            ),
+            YoloLabel::Segment(segment) => (
+                segment
+                    .points
+                    .iter()
+                    .map(|p| vec![p[0] * w, p[1] * h])
+                    .collect(),
+                ShapeType::Polygon,
+            ),
+        };
+        Ok(Shape {
+            label,
+            points,
+            group_id: None,
+            shape_type,
+            flags: Some(HashMap::new()),
+            extra: serde_json::Map::new(),
+        })
+    }
+}
+
+/// yolo segmentation txt export format,
+/// `class x1 y1 x2 y2 ... xn yn` with normalized vertices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YoloSegAnnotation {
+    pub class: i64,
+    pub points: Vec<Vec<f32>>,
 }
 
 /// parsed directrly from the json file eh
@@ -352,7 +453,7 @@ impl LabelmeAnnotation {
 ///    label: String,
 ///    points: Vec<Vec<f32>>,
 ///    group_id: Option<String>,
-///    shape_type: String,
+///    shape_type: ShapeType,
 ///    flags: HashMap<String, String>,
 ///  }`
 ///
@@ -361,32 +462,199 @@ pub struct Shape {
     pub label: String,
     pub points: Vec<Vec<f32>>,
     pub group_id: Option<String>,
-    pub shape_type: String,
+    pub shape_type: ShapeType,
     pub flags: Option<HashMap<String, String>>,
+    /// keys kesa doesn't model (`description`, `mask`, ...), written back untouched
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+/// vertices used when a circle has to be
+/// approximated as a polygon
+const CIRCLE_VERTICES: usize = 32;
+
 impl Shape {
     pub fn update_points_from_xyxy(&mut self, new_xyxy: Xyxy) {
         let x1y1 = vec![new_xyxy.x1, new_xyxy.y1];
         let x2y2 = vec![new_xyxy.x2, new_xyxy.y2];
         self.points = vec![x1y1, x2y2];
     }
+
+    /// true if `flags[key]` is `"true"` or `"1"`
+    pub fn flag_is_set(&self, key: &str) -> bool {
+        matches!(
+            self.flags.as_ref().and_then(|f| f.get(key)).map(|v| v.as_str()),
+            Some("true") | Some("1")
+        )
+    }
+
+    /// sets `flags[key]` to `"true"`
+    pub fn set_flag(&mut self, key: &str) {
+        self.flags
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from("true"));
+    }
+
+    /// tight screen bbox around the shape, corners are
+    /// always ordered so that x1 <= x2 and y1 <= y2.
+    ///
+    /// circles are stored by labelme as `[center, point_on_edge]`
+    pub fn bbox(&self) -> Result<Xyxy, Error> {
+        let min_points = match self.shape_type.min_points() {
+            Some(min_points) => min_points,
+            None => bail!(
+                "[error]::label: {:?} shape {:?} isn't supported",
+                self.shape_type,
+                self.label
+            ),
+        };
+        if self.points.len() < min_points {
+            bail!(
+                "[error]::label: {:?} shape {:?} needs at least {} points, got {}",
+                self.shape_type,
+                self.label,
+                min_points,
+                self.points.len()
+            );
+        }
+        match self.shape_type {
+            ShapeType::Circle => {
+                let (cx, cy) = (self.points[0][0], self.points[0][1]);
+                let radius = (self.points[1][0] - cx).hypot(self.points[1][1] - cy);
+                Ok(Xyxy::new(
+                    CoordinateType::Screen,
+                    cx - radius,
+                    cy - radius,
+                    cx + radius,
+                    cy + radius,
+                ))
+            }
+            _ => {
+                let mut xyxy = Xyxy::new(
+                    CoordinateType::Screen,
+                    f32::MAX,
+                    f32::MAX,
+                    f32::MIN,
+                    f32::MIN,
+                );
+                for point in self.points.iter() {
+                    xyxy.x1 = xyxy.x1.min(point[0]);
+                    xyxy.y1 = xyxy.y1.min(point[1]);
+                    xyxy.x2 = xyxy.x2.max(point[0]);
+                    xyxy.y2 = xyxy.y2.max(point[1]);
+                }
+                Ok(xyxy)
+            }
+        }
+    }
+
+    /// outline of the shape as polygon vertices,
+    /// rectangles become their 4 corners and circles
+    /// are approximated with `CIRCLE_VERTICES` points
+    pub fn polygon(&self) -> Result<Vec<Vec<f32>>, Error> {
+        match self.shape_type {
+            ShapeType::Rectangle => {
+                let b = self.bbox()?;
+                Ok(vec![
+                    vec![b.x1, b.y1],
+                    vec![b.x2, b.y1],
+                    vec![b.x2, b.y2],
+                    vec![b.x1, b.y2],
+                ])
+            }
+            ShapeType::Circle => {
+                let b = self.bbox()?;
+                let center = b.get_center_xy();
+                let radius = (b.x2 - b.x1) / 2.0;
+                Ok((0..CIRCLE_VERTICES)
+                    .map(|i| {
+                        let t = i as f32 / CIRCLE_VERTICES as f32 * std::f32::consts::TAU;
+                        vec![center[0] + radius * t.cos(), center[1] + radius * t.sin()]
+                    })
+                    .collect())
+            }
+            _ => {
+                self.bbox()?;
+                Ok(self.points.to_owned())
+            }
+        }
+    }
 }
 
-pub fn get_xyxy_from_shape(input_shape: &Shape, coordinate_type: CoordinateType) -> Xyxy {
-    Xyxy {
-        coordinate_type,
-        x1: input_shape.points[0][0].to_owned(),
-        y1: input_shape.points[0][1].to_owned(),
-        x2: input_shape.points[1][0].to_owned(),
-        y2: input_shape.points[1][1].to_owned(),
+/// area enclosed by polygon vertices (shoelace formula)
+pub fn polygon_area(points: &[Vec<f32>]) -> f32 {
+    let mut area = 0.0;
+    for (idx, p) in points.iter().enumerate() {
+        let q = &points[(idx + 1) % points.len()];
+        area += p[0] * q[1] - q[0] * p[1];
     }
+    (area / 2.0).abs()
 }
 
-/// TODO: SHAPE MASK  for segmentation tasks , unimplemnetd for now :|
+/// bbox of a shape, see `Shape::bbox`
+pub fn get_xyxy_from_shape(
+    input_shape: &Shape,
+    coordinate_type: CoordinateType,
+) -> Result<Xyxy, Error> {
+    let mut xyxy = input_shape.bbox()?;
+    xyxy.coordinate_type = coordinate_type;
+    Ok(xyxy)
+}
+
+/// labelme `shape_type`s
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ShapeType {
     Rectangle,
-    Mask,
+    Polygon,
+    Circle,
+    Point,
+    Line,
+    #[serde(rename = "linestrip")]
+    LineStrip,
+    /// types kesa doesn't handle (labelme 5 `mask`, `points`, ...),
+    /// read and written back as they are but never converted
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl ShapeType {
+    /// shapes that cover an area and can be
+    /// exported as a detection box or segment
+    pub fn has_area(&self) -> bool {
+        matches!(
+            self,
+            ShapeType::Rectangle | ShapeType::Polygon | ShapeType::Circle
+        )
+    }
+
+    /// least amount of points needed to describe the shape,
+    /// none for the types kesa doesn't handle
+    pub fn min_points(&self) -> Option<usize> {
+        match self {
+            ShapeType::Point => Some(1),
+            ShapeType::Polygon => Some(3),
+            ShapeType::Other(_) => None,
+            _ => Some(2),
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, ShapeType::Other(_))
+    }
+}
+
+/// true (after a warning) for shapes of a type kesa can't convert,
+/// exporters skip them instead of failing the whole file
+pub fn skip_unsupported(shape: &Shape) -> bool {
+    if let ShapeType::Other(shape_type) = &shape.shape_type {
+        println!(
+            "[warning]::label: skipping {:?}, {:?} shapes aren't supported",
+            &shape.label, shape_type
+        );
+        return true;
+    }
+    false
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -430,7 +698,7 @@ impl OutputFormat for Embeddings {
         let raw_output_vec = self.to_yolo_vec();
         let g_id = self.to_vec()?;
         let mut shape_vec: Vec<Shape> = vec![];
-        let shape = String::from("rectangle");
+        let shape = ShapeType::Rectangle;
         let flags: HashMap<String, String> = HashMap::new();
         for _yolo in raw_output_vec.into_iter() {
             for (idx, elem) in _yolo.iter().enumerate() {
@@ -449,6 +717,7 @@ impl OutputFormat for Embeddings {
                     shape_type: shape.to_owned(),
                     group_id: Some(gid_idx.to_string()),
                     flags: Some(flags.to_owned()),
+                    extra: serde_json::Map::new(),
                 };
                 shape_vec.push(_shape);
             }
@@ -495,10 +764,11 @@ impl OutputFormat for Embeddings {
         Ok(LabelmeAnnotation {
             version,
             flags: Some(flags),
+            extra: serde_json::Map::new(),
             shapes: all_shapes,
             imageWidth: original_dimension.0.to_owned() as i64,
             imageHeight: original_dimension.1.to_owned() as i64,
-            imageData: base64img,
+            imageData: Some(base64img),
             // TODO: change to filename instead
             // of the whole mf directory
             imagePath: _file.file_name().unwrap().to_string_lossy().to_string(),
@@ -507,9 +777,14 @@ impl OutputFormat for Embeddings {
 }
 
 pub fn read_labels_from_file(filename: &str) -> Result<LabelmeAnnotation, Error> {
-    let json_filename = fs::read_to_string(filename).expect("READ ERROR: cannot read jsonfile !");
-    let read_json_to_struct: LabelmeAnnotation = serde_json::from_str(&json_filename)
-        .expect("LABELME ERROR: cannot read json to label me struct!");
+    let json_filename = match fs::read_to_string(filename) {
+        Ok(json_str) => json_str,
+        Err(e) => bail!("[error]::label: cannot read {:?}: {}", filename, e),
+    };
+    let read_json_to_struct: LabelmeAnnotation = match serde_json::from_str(&json_filename) {
+        Ok(labelme) => labelme,
+        Err(e) => bail!("[error]::label: {:?} is not a labelme json: {}", filename, e),
+    };
     Ok(read_json_to_struct)
 }
 
@@ -538,4 +813,85 @@ mod test_read_labels_from_file {
         dbg!("yolo: {:?}", &_yolo);
         assert_eq!(_yolo.len(), 4);
     }
+
+    #[test]
+    fn yolo_label_to_shape() {
+        let _classes = vec![String::from("a"), String::from("b")];
+        let _bbox = YoloLabel::from_line("1 0.5 0.25 0.25 0.125").unwrap();
+        let _shape = _bbox.to_shape(&_classes, &(200, 100)).unwrap();
+        assert_eq!(_shape.label, "b");
+        assert_eq!(_shape.points, vec![vec![75.0, 18.75], vec![125.0, 31.25]]);
+
+        let _segment = YoloLabel::from_line("0 0.1 0.1 0.5 0.1 0.5 0.5").unwrap();
+        let _shape = _segment.to_shape(&_classes, &(200, 100)).unwrap();
+        assert_eq!(_shape.shape_type, ShapeType::Polygon);
+        assert_eq!(_shape.points[2], vec![100.0, 50.0]);
+
+        assert!(YoloLabel::from_line("0 0.1 0.1 0.5").is_err());
+        assert!(YoloLabel::from_line("5 0.5 0.5 0.1 0.1")
+            .unwrap()
+            .to_shape(&_classes, &(200, 100))
+            .is_err());
+    }
+
+    #[test]
+    fn polygon_shapes() {
+        let _polygon: Shape = serde_json::from_str(
+            r#"{"label": "eye", "points": [[10.0, 20.0], [50.0, 5.0], [30.0, 40.0]],
+                "group_id": null, "shape_type": "polygon", "flags": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(_polygon.shape_type, ShapeType::Polygon);
+        let _bbox = _polygon.bbox().unwrap();
+        assert_eq!(
+            (_bbox.x1, _bbox.y1, _bbox.x2, _bbox.y2),
+            (10.0, 5.0, 50.0, 40.0)
+        );
+
+        let mut _circle = _polygon.to_owned();
+        _circle.shape_type = ShapeType::Circle;
+        _circle.points = vec![vec![50.0, 50.0], vec![60.0, 50.0]];
+        let _bbox = _circle.bbox().unwrap();
+        assert_eq!(
+            (_bbox.x1, _bbox.y1, _bbox.x2, _bbox.y2),
+            (40.0, 40.0, 60.0, 60.0)
+        );
+
+        let mut _point = _polygon.to_owned();
+        _point.shape_type = ShapeType::Point;
+        _point.points = vec![vec![1.0, 1.0]];
+
+        // labelme 5 masks are read, kept as they are and skipped by the exports
+        let _mask: Shape = serde_json::from_str(
+            r#"{"label": "eye", "points": [[10.0, 20.0], [50.0, 40.0]],
+                "group_id": null, "shape_type": "mask", "flags": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(_mask.shape_type, ShapeType::Other(String::from("mask")));
+        assert!(_mask.bbox().is_err());
+        assert!(serde_json::to_string(&_mask)
+            .unwrap()
+            .contains(r#""shape_type":"mask""#));
+
+        let _anno = LabelmeAnnotation::new(
+            None,
+            vec![_polygon, _circle, _point, _mask],
+            String::from("dummy.png"),
+            None,
+            100,
+            100,
+        );
+        let mut _hash = HashMap::new();
+        _hash.insert(String::from("eye"), 0);
+        let _yolo = _anno.to_yolo(&_hash).unwrap();
+        assert_eq!(_yolo.len(), 2);
+        assert_eq!((_yolo[0].xmin, _yolo[0].w), (0.3, 0.4));
+        let _seg = _anno.to_yolo_seg(&_hash).unwrap();
+        assert_eq!(_seg.len(), 2);
+        assert_eq!(
+            _seg[0].points,
+            vec![vec![0.1, 0.2], vec![0.5, 0.05], vec![0.3, 0.4]]
+        );
+        assert_eq!(_seg[1].points.len(), 32);
+    }
 }
@@ -1,9 +1,20 @@
 pub mod backends;
+pub mod class_map;
+pub mod coco;
+pub mod dataset;
+pub mod dedup;
 pub mod fileutils;
+pub mod formats;
 pub mod image_augmentations;
 pub mod image_utils;
 pub mod label;
+pub mod merge;
 pub mod model;
 pub mod output;
 pub mod plotting;
+pub mod repair;
 mod splash;
+pub mod split;
+pub mod stats;
+pub mod validate;
+pub mod voc;
@@ -0,0 +1,237 @@
+/* combines several datasets into one with a single class index */
+use crate::class_map::ClassMap;
+use crate::dataset::{Dataset, DatasetItem};
+use crate::fileutils::content_hash;
+use anyhow::{Error, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// name of the dataset an image came from
+pub const SOURCE_DATASET_FLAG: &str = "source_dataset";
+/// path of the image inside its dataset
+pub const SOURCE_IMAGE_FLAG: &str = "source_image";
+/// `<dataset>/<image>` of identical images that were left out, comma separated
+pub const DUPLICATES_FLAG: &str = "duplicates";
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// renames/merges/drops classes of every dataset before merging
+    pub class_map: Option<ClassMap>,
+    /// keep identical images instead of only the first one
+    pub keep_duplicates: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MergeReport {
+    pub images: usize,
+    pub duplicates: usize,
+    /// images that got their dataset name as prefix
+    pub renamed: usize,
+    pub shapes_dropped: usize,
+    /// images taken from each dataset
+    pub sources: BTreeMap<String, usize>,
+    pub classes: Vec<String>,
+}
+
+/// a unique name for every dataset, from its folder name
+pub fn source_names(folders: &[PathBuf]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for (idx, folder) in folders.iter().enumerate() {
+        let base = std::path::absolute(folder)
+            .ok()
+            .and_then(|folder| folder.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| format!("dataset{}", idx));
+        let mut name = base.to_owned();
+        let mut count = 1;
+        while names.contains(&name) {
+            count += 1;
+            name = format!("{}_{}", base, count);
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// merges `(name, dataset)` pairs in order into a dataset rooted at `output`.
+/// images keep their file name unless an earlier dataset already used it
+/// for a different image, then they get `<name>_` in front.
+/// the merged items still read their images from the source datasets,
+/// `train`/`val`/`test` splits are only kept if every image has one
+pub fn merge_datasets(
+    sources: &[(String, Dataset)],
+    output: &Path,
+    options: &MergeOptions,
+) -> Result<(Dataset, MergeReport), Error> {
+    let mut merged = Dataset::new(output);
+    let mut report = MergeReport::default();
+    if let Some(class_map) = &options.class_map {
+        merged.classes = class_map.order.to_owned();
+    }
+
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    let mut used_names: HashSet<PathBuf> = HashSet::new();
+    for (name, dataset) in sources.iter() {
+        let hashes = dataset
+            .items
+            .par_iter()
+            .map(|item| content_hash(&dataset.image_source(item)))
+            .collect::<Result<Vec<String>, Error>>()?;
+        for (item, hash) in dataset.items.iter().zip(hashes) {
+            let source_image = item.image_path.to_string_lossy().to_string();
+            if !options.keep_duplicates {
+                if let Some(idx) = kept.get(&hash) {
+                    let duplicates = merged.items[*idx]
+                        .flags
+                        .entry(String::from(DUPLICATES_FLAG))
+                        .or_default();
+                    if !duplicates.is_empty() {
+                        duplicates.push(',');
+                    }
+                    duplicates.push_str(&format!("{}/{}", name, source_image));
+                    report.duplicates += 1;
+                    continue;
+                }
+            }
+
+            let mut merged_item: DatasetItem = item.to_owned();
+            merged_item.source = Some(dataset.image_source(item));
+            if let Some(class_map) = &options.class_map {
+                let shape_count = merged_item.shapes.len();
+                merged_item.shapes = merged_item
+                    .shapes
+                    .into_iter()
+                    .filter_map(|mut shape| {
+                        shape.label = class_map.map_label(&shape.label)?;
+                        Some(shape)
+                    })
+                    .collect();
+                report.shapes_dropped += shape_count - merged_item.shapes.len();
+            }
+
+            let file_name = item.image_path.file_name().unwrap_or_default();
+            let mut image_path = PathBuf::from(file_name);
+            if used_names.contains(&image_path) {
+                report.renamed += 1;
+                image_path = PathBuf::from(format!("{}_{}", name, file_name.to_string_lossy()));
+                let mut count = 1;
+                while used_names.contains(&image_path) {
+                    count += 1;
+                    image_path = PathBuf::from(format!(
+                        "{}_{}_{}",
+                        name,
+                        count,
+                        file_name.to_string_lossy()
+                    ));
+                }
+            }
+            used_names.insert(image_path.to_owned());
+            merged_item.image_path = image_path;
+            merged_item
+                .flags
+                .insert(String::from(SOURCE_DATASET_FLAG), name.to_owned());
+            merged_item
+                .flags
+                .insert(String::from(SOURCE_IMAGE_FLAG), source_image);
+
+            kept.insert(hash, merged.items.len());
+            merged.items.push(merged_item);
+            *report.sources.entry(name.to_owned()).or_insert(0) += 1;
+        }
+    }
+    // a half split dataset would be written as two layouts
+    if merged.items.iter().any(|item| item.split.is_none()) {
+        merged.items.iter_mut().for_each(|item| item.split = None);
+    }
+    merged.collect_classes();
+    report.images = merged.items.len();
+    report.classes = merged.classes.to_owned();
+    Ok((merged, report))
+}
+
+#[cfg(test)]
+mod test_merge {
+    use crate::formats::annotation_format::get_format;
+    use crate::merge::*;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn merge_dedupes_and_prefixes() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        for _team in ["team_a", "team_b"] {
+            fs::create_dir_all(_tmp.join(_team)).unwrap();
+            fs::copy("test/test.json", _tmp.join(_team).join("test.json")).unwrap();
+            fs::copy("test/test.png", _tmp.join(_team).join("test.png")).unwrap();
+        }
+        // same name, different image
+        let mut _other = fs::read("test/test.png").unwrap();
+        _other.push(0);
+        fs::write(_tmp.join("team_b/other.png"), &_other).unwrap();
+        fs::write(
+            _tmp.join("team_b/other.json"),
+            fs::read_to_string("test/test.json")
+                .unwrap()
+                .replace("\"test.png\"", "\"other.png\""),
+        )
+        .unwrap();
+        fs::create_dir_all(_tmp.join("team_c")).unwrap();
+        _other.push(0);
+        fs::write(_tmp.join("team_c/other.png"), &_other).unwrap();
+        fs::write(
+            _tmp.join("team_c/other.json"),
+            fs::read_to_string("test/test.json")
+                .unwrap()
+                .replace("\"test.png\"", "\"other.png\"")
+                .replace("\"eye\"", "\"eyes\""),
+        )
+        .unwrap();
+
+        let _folders: Vec<PathBuf> = ["team_a", "team_b", "team_c"]
+            .iter()
+            .map(|t| _tmp.join(t))
+            .collect();
+        let _sources: Vec<(String, Dataset)> = source_names(&_folders)
+            .into_iter()
+            .zip(_folders.iter())
+            .map(|(_name, _folder)| {
+                (
+                    _name,
+                    get_format("labelme")
+                        .unwrap()
+                        .read_dataset(_folder)
+                        .unwrap(),
+                )
+            })
+            .collect();
+        let _map = ClassMap::from_yaml("rules: [{from: eyes, to: eye}]", Path::new("")).unwrap();
+        let _options = MergeOptions {
+            class_map: Some(_map),
+            keep_duplicates: false,
+        };
+        let (_merged, _report) = merge_datasets(&_sources, &_tmp.join("out"), &_options).unwrap();
+        assert_eq!(
+            (_report.images, _report.duplicates, _report.renamed),
+            (3, 1, 1)
+        );
+        assert_eq!(_report.classes, vec!["ear", "eye", "mouth"]);
+        let _names: Vec<String> = _merged
+            .items
+            .iter()
+            .map(|i| i.image_path.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(_names, vec!["test.png", "other.png", "team_c_other.png"]);
+        assert_eq!(_merged.items[0].flags[DUPLICATES_FLAG], "team_b/test.png");
+        assert_eq!(_merged.items[2].flags[SOURCE_DATASET_FLAG], "team_c");
+        assert_eq!(_merged.items[2].flags[SOURCE_IMAGE_FLAG], "other.png");
+
+        get_format("labelme")
+            .unwrap()
+            .write_dataset(&_merged, &_tmp.join("out"))
+            .unwrap();
+        assert!(_tmp.join("out/team_c_other.json").exists());
+        assert_eq!(fs::read(_tmp.join("out/team_c_other.png")).unwrap(), _other);
+        fs::remove_dir_all(_tmp).unwrap();
+    }
+}
@@ -1,5 +1,154 @@
-use plotters;
+/* chart rendering with plotters, the backend is picked from the file extension */
+use anyhow::{anyhow, Error, Result};
+use clap::ValueEnum;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::path::Path;
 
-pub fn draw_dummy_graph() {
-    todo!()
+pub const CHART_SIZE: (u32, u32) = (1024, 768);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum ChartFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl ChartFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+        }
+    }
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("svg"))
+        .unwrap_or(false)
+}
+
+/// one bar per label, written to `path` as png or svg
+pub fn draw_bar_chart(
+    path: &Path,
+    title: &str,
+    labels: &[String],
+    values: &[usize],
+) -> Result<(), Error> {
+    match is_svg(path) {
+        true => bar_chart(
+            SVGBackend::new(path, CHART_SIZE).into_drawing_area(),
+            title,
+            labels,
+            values,
+        ),
+        false => bar_chart(
+            BitMapBackend::new(path, CHART_SIZE).into_drawing_area(),
+            title,
+            labels,
+            values,
+        ),
+    }
+}
+
+/// `rows` x `columns` grid of counts, the first row is drawn at the top
+pub fn draw_heatmap(path: &Path, title: &str, grid: &[Vec<usize>]) -> Result<(), Error> {
+    match is_svg(path) {
+        true => heatmap(
+            SVGBackend::new(path, CHART_SIZE).into_drawing_area(),
+            title,
+            grid,
+        ),
+        false => heatmap(
+            BitMapBackend::new(path, CHART_SIZE).into_drawing_area(),
+            title,
+            grid,
+        ),
+    }
+}
+
+fn bar_chart<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    labels: &[String],
+    values: &[usize],
+) -> Result<(), Error> {
+    let to_error = |e: DrawingAreaErrorKind<DB::ErrorType>| anyhow!("[error]::plotting: {}", e);
+    root.fill(&WHITE).map_err(to_error)?;
+    let max_value = values.iter().max().copied().unwrap_or(0).max(1);
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 28))
+        .margin(20)
+        .x_label_area_size(60)
+        .y_label_area_size(60)
+        // segmented ranges include their end
+        .build_cartesian_2d(
+            (0..labels.len().saturating_sub(1)).into_segmented(),
+            0..max_value + max_value / 10 + 1,
+        )
+        .map_err(to_error)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(labels.len().max(1))
+        .x_label_style(("sans-serif", 16))
+        .x_label_formatter(&|value| match value {
+            SegmentValue::CenterOf(idx) => labels.get(*idx).cloned().unwrap_or_default(),
+            _ => String::new(),
+        })
+        .draw()
+        .map_err(to_error)?;
+    chart
+        .draw_series(
+            Histogram::vertical(&chart)
+                .style(BLUE.mix(0.7).filled())
+                .margin(4)
+                .data(values.iter().enumerate().map(|(idx, value)| (idx, *value))),
+        )
+        .map_err(to_error)?;
+    root.present().map_err(to_error)?;
+    Ok(())
+}
+
+fn heatmap<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    grid: &[Vec<usize>],
+) -> Result<(), Error> {
+    let to_error = |e: DrawingAreaErrorKind<DB::ErrorType>| anyhow!("[error]::plotting: {}", e);
+    root.fill(&WHITE).map_err(to_error)?;
+    let rows = grid.len().max(1);
+    let columns = grid.iter().map(|row| row.len()).max().unwrap_or(0).max(1);
+    let max_value = grid.iter().flatten().max().copied().unwrap_or(0).max(1);
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 28))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0.0..1.0f64, 1.0..0.0f64)
+        .map_err(to_error)?;
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .draw()
+        .map_err(to_error)?;
+    let (cell_w, cell_h) = (1.0 / columns as f64, 1.0 / rows as f64);
+    chart
+        .draw_series(grid.iter().enumerate().flat_map(|(row, values)| {
+            values.iter().enumerate().map(move |(column, value)| {
+                let intensity = *value as f64 / max_value as f64;
+                let color = HSLColor(0.66 * (1.0 - intensity), 1.0, 0.25 + 0.35 * intensity);
+                Rectangle::new(
+                    [
+                        (column as f64 * cell_w, row as f64 * cell_h),
+                        ((column + 1) as f64 * cell_w, (row + 1) as f64 * cell_h),
+                    ],
+                    color.filled(),
+                )
+            })
+        }))
+        .map_err(to_error)?;
+    root.present().map_err(to_error)?;
+    Ok(())
 }
@@ -0,0 +1,524 @@
+/* fixes for the common labelme problems found by `validate` */
+use crate::fileutils::{
+    export_file, find_files, find_image_for, relative_path, DiscoveryOptions, ExportMode,
+};
+use crate::label::{polygon_area, LabelmeAnnotation, Shape, ShapeType};
+use anyhow::{Error, Result};
+use clap::ValueEnum;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// every fix `repair_annotation` can apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairFix {
+    /// point `imagePath` to an image with the json's name if it's missing
+    ImagePath,
+    /// set `imageWidth`/`imageHeight` from the image file
+    Dimensions,
+    /// rectangles drawn right to left get `x1 <= x2`, `y1 <= y2`
+    Reorder,
+    /// clamp points to the image, circles are left alone
+    Clip,
+    /// remove shapes without enough points or without area
+    DropDegenerate,
+    /// remove shapes that are exactly the same as an earlier one
+    Dedupe,
+}
+
+impl RepairFix {
+    pub fn all() -> Vec<RepairFix> {
+        vec![
+            RepairFix::ImagePath,
+            RepairFix::Dimensions,
+            RepairFix::Reorder,
+            RepairFix::Clip,
+            RepairFix::DropDegenerate,
+            RepairFix::Dedupe,
+        ]
+    }
+}
+
+/// one change made to a file, `before`/`after` are
+/// short descriptions used for the dry run diff
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepairChange {
+    pub file: String,
+    pub fix: RepairFix,
+    /// index of the shape in the original file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<usize>,
+    pub before: String,
+    pub after: String,
+}
+
+/// files that couldn't be repaired, e.g. broken json
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepairSkip {
+    pub file: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepairReport {
+    pub dry_run: bool,
+    pub files_checked: usize,
+    pub files_changed: usize,
+    pub changes: Vec<RepairChange>,
+    pub skipped: Vec<RepairSkip>,
+    /// problems left in files that got the other fixes, e.g. a missing image
+    pub unfixed: Vec<RepairSkip>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepairOptions {
+    pub fixes: Vec<RepairFix>,
+    /// only report what would change
+    pub dry_run: bool,
+    /// write the repaired dataset here instead of in place
+    pub output: Option<PathBuf>,
+    pub discovery: DiscoveryOptions,
+}
+
+/// changes of one file, why it was skipped and what was left unfixed
+type FileRepair = (Vec<RepairChange>, Option<RepairSkip>, Option<RepairSkip>);
+
+fn describe_points(points: &[Vec<f32>]) -> String {
+    format!("{:?}", points)
+}
+
+/// applies the fixes to one annotation, `json` is where it was read from.
+/// returns what changed and what couldn't be fixed. only `Dimensions` needs
+/// the image, the other fixes use the json's sizes when it's missing
+pub fn repair_annotation(
+    labelme: &mut LabelmeAnnotation,
+    json: &Path,
+    fixes: &[RepairFix],
+) -> (Vec<RepairChange>, Option<String>) {
+    let mut changes: Vec<RepairChange> = vec![];
+    let file = json.to_string_lossy().to_string();
+    let mut change = |fix: RepairFix, shape: Option<usize>, before: String, after: String| {
+        changes.push(RepairChange {
+            file: file.to_owned(),
+            fix,
+            shape,
+            before,
+            after,
+        })
+    };
+    let folder = json.parent().unwrap_or(Path::new(""));
+
+    let mut unfixed: Option<String> = None;
+    let mut image = folder.join(&labelme.imagePath);
+    if !image.is_file() {
+        match (fixes.contains(&RepairFix::ImagePath), find_image_for(json)) {
+            (true, Some(found)) => {
+                let image_path = found
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                change(
+                    RepairFix::ImagePath,
+                    None,
+                    labelme.imagePath.to_owned(),
+                    image_path.to_owned(),
+                );
+                labelme.imagePath = image_path;
+                image = found;
+            }
+            _ => unfixed = Some(format!("image {:?} doesn't exist", &labelme.imagePath)),
+        }
+    }
+
+    if fixes.contains(&RepairFix::Dimensions) && unfixed.is_none() {
+        match image::image_dimensions(&image) {
+            Ok((w, h)) if w as i64 != labelme.imageWidth || h as i64 != labelme.imageHeight => {
+                change(
+                    RepairFix::Dimensions,
+                    None,
+                    format!("{}x{}", labelme.imageWidth, labelme.imageHeight),
+                    format!("{}x{}", w, h),
+                );
+                labelme.imageWidth = w as i64;
+                labelme.imageHeight = h as i64;
+            }
+            Ok(_) => {}
+            Err(e) => unfixed = Some(format!("cannot read {:?}: {}", image, e)),
+        }
+    }
+
+    let (w, h) = (labelme.imageWidth as f32, labelme.imageHeight as f32);
+    let mut kept: Vec<Shape> = vec![];
+    for (idx, shape) in labelme.shapes.iter().enumerate() {
+        let mut shape = shape.to_owned();
+        let min_points = match shape.shape_type.min_points() {
+            Some(min_points) => min_points,
+            // types kesa doesn't know are kept as they are
+            None => {
+                kept.push(shape);
+                continue;
+            }
+        };
+        let malformed = shape.points.len() < min_points || shape.points.iter().any(|p| p.len() < 2);
+        if malformed {
+            if fixes.contains(&RepairFix::DropDegenerate) {
+                change(
+                    RepairFix::DropDegenerate,
+                    Some(idx),
+                    format!("{:?} {}", &shape.label, describe_points(&shape.points)),
+                    String::from("removed, not enough points"),
+                );
+                continue;
+            }
+            // nothing else can be done to it safely
+            kept.push(shape);
+            continue;
+        }
+
+        if fixes.contains(&RepairFix::Reorder) && shape.shape_type == ShapeType::Rectangle {
+            if let Ok(bbox) = shape.bbox() {
+                let ordered = vec![vec![bbox.x1, bbox.y1], vec![bbox.x2, bbox.y2]];
+                if shape.points != ordered {
+                    let before = describe_points(&shape.points);
+                    shape.update_points_from_xyxy(bbox);
+                    change(
+                        RepairFix::Reorder,
+                        Some(idx),
+                        before,
+                        describe_points(&shape.points),
+                    );
+                }
+            }
+        }
+
+        if fixes.contains(&RepairFix::Clip) && shape.shape_type != ShapeType::Circle {
+            let clipped: Vec<Vec<f32>> = shape
+                .points
+                .iter()
+                .map(|p| {
+                    let mut clipped = p.to_owned();
+                    clipped[0] = p[0].clamp(0.0, w);
+                    clipped[1] = p[1].clamp(0.0, h);
+                    clipped
+                })
+                .collect();
+            if clipped != shape.points {
+                change(
+                    RepairFix::Clip,
+                    Some(idx),
+                    describe_points(&shape.points),
+                    describe_points(&clipped),
+                );
+                shape.points = clipped;
+            }
+        }
+
+        if fixes.contains(&RepairFix::DropDegenerate) && shape.shape_type.has_area() {
+            let degenerate = match (shape.bbox(), shape.polygon()) {
+                (Ok(bbox), Ok(polygon)) => {
+                    bbox.x2 - bbox.x1 <= 0.0
+                        || bbox.y2 - bbox.y1 <= 0.0
+                        || polygon_area(&polygon) <= 0.0
+                }
+                _ => true,
+            };
+            if degenerate {
+                change(
+                    RepairFix::DropDegenerate,
+                    Some(idx),
+                    format!("{:?} {}", &shape.label, describe_points(&shape.points)),
+                    String::from("removed, no area"),
+                );
+                continue;
+            }
+        }
+
+        if fixes.contains(&RepairFix::Dedupe) && kept.contains(&shape) {
+            change(
+                RepairFix::Dedupe,
+                Some(idx),
+                format!("{:?} {}", &shape.label, describe_points(&shape.points)),
+                String::from("removed, duplicate"),
+            );
+            continue;
+        }
+        kept.push(shape);
+    }
+    labelme.shapes = kept;
+    (changes, unfixed)
+}
+
+/// repairs every labelme json in a folder, in place or into `options.output`.
+///
+/// with an output folder every json and its image is copied there,
+/// changed or not, so the output is a complete dataset. files that can't
+/// be read as labelme are copied as they are, jsons without an image alone
+pub fn repair_folder(folder: &Path, options: &RepairOptions) -> Result<RepairReport, Error> {
+    let mut discovery = options.discovery.to_owned();
+    if let Some(output) = &options.output {
+        discovery.skip_folders.push(output.to_owned());
+    }
+    let all_json = find_files(folder, &["json"], &discovery)?;
+
+    let results = all_json
+        .par_iter()
+        .map(|json| -> Result<FileRepair, Error> {
+            let skip = |reason: String| RepairSkip {
+                file: json.to_string_lossy().to_string(),
+                reason,
+            };
+            let json_dest = options
+                .output
+                .as_ref()
+                .map(|output| output.join(relative_path(folder, json)));
+            let mut labelme: LabelmeAnnotation = match fs::read_to_string(json)
+                .map_err(Error::from)
+                .and_then(|json_str| Ok(serde_json::from_str(&json_str)?))
+            {
+                Ok(labelme) => labelme,
+                Err(e) => {
+                    if let (Some(json_dest), false) = (&json_dest, options.dry_run) {
+                        export_file(json, json_dest, ExportMode::Copy)?;
+                        if let Some(image) = find_image_for(json) {
+                            export_file(
+                                &image,
+                                &json_dest.with_file_name(image.file_name().unwrap_or_default()),
+                                ExportMode::Copy,
+                            )?;
+                        }
+                    }
+                    return Ok((
+                        vec![],
+                        Some(skip(format!("not a labelme json: {}", e))),
+                        None,
+                    ));
+                }
+            };
+            let (mut changes, unfixed) = repair_annotation(&mut labelme, json, &options.fixes);
+
+            let image = json
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&labelme.imagePath);
+            let image_dest = match (&options.output, &json_dest) {
+                (Some(output), Some(json_dest)) if image.is_file() => {
+                    // `..` in imagePath can lead out of the folder
+                    let inside = match (fs::canonicalize(&image), fs::canonicalize(folder)) {
+                        (Ok(image), Ok(folder)) => image
+                            .strip_prefix(&folder)
+                            .map(|relative| relative.to_path_buf())
+                            .ok(),
+                        _ => None,
+                    };
+                    match inside {
+                        Some(relative) => Some(output.join(relative)),
+                        // images outside the folder end up next to the json
+                        None => {
+                            let image_path = image
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .to_string();
+                            changes.push(RepairChange {
+                                file: json.to_string_lossy().to_string(),
+                                fix: RepairFix::ImagePath,
+                                shape: None,
+                                before: labelme.imagePath.to_owned(),
+                                after: image_path.to_owned(),
+                            });
+                            labelme.imagePath = image_path;
+                            Some(json_dest.with_file_name(&labelme.imagePath))
+                        }
+                    }
+                }
+                _ => None,
+            };
+            let unfixed = unfixed.map(skip);
+            if options.dry_run {
+                return Ok((changes, None, unfixed));
+            }
+            match &json_dest {
+                Some(json_dest) => {
+                    if let Some(image_dest) = &image_dest {
+                        export_file(&image, image_dest, ExportMode::Copy)?;
+                    }
+                    if let Some(parent) = json_dest.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(json_dest, serde_json::to_string_pretty(&labelme)?)?;
+                }
+                None if !changes.is_empty() => {
+                    fs::write(json, serde_json::to_string_pretty(&labelme)?)?;
+                }
+                None => {}
+            }
+            Ok((changes, None, unfixed))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut changes: Vec<RepairChange> = vec![];
+    let mut skipped: Vec<RepairSkip> = vec![];
+    let mut unfixed: Vec<RepairSkip> = vec![];
+    let mut files_changed = 0;
+    for (file_changes, skip, problem) in results.into_iter() {
+        if !file_changes.is_empty() {
+            files_changed += 1;
+        }
+        changes.extend(file_changes);
+        skipped.extend(skip);
+        unfixed.extend(problem);
+    }
+    Ok(RepairReport {
+        dry_run: options.dry_run,
+        files_checked: all_json.len(),
+        files_changed,
+        changes,
+        skipped,
+        unfixed,
+    })
+}
+
+#[cfg(test)]
+mod test_repair {
+    use crate::label::read_labels_from_file;
+    use crate::repair::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn repair_fixes_shapes() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&_tmp).unwrap();
+        fs::copy("test/test.png", _tmp.join("frame.png")).unwrap();
+        let _json = _tmp.join("frame.json");
+
+        let mut _labelme = read_labels_from_file("test/test.json").unwrap();
+        let _orig = _labelme.to_owned();
+        _labelme.imagePath = String::from("renamed.png");
+        _labelme.imageWidth = 512;
+        // drawn right to left
+        _labelme.shapes[0].points = vec![vec![300.0, 400.0], vec![100.0, 200.0]];
+        // sticks out of the image
+        _labelme.shapes[1].points = vec![vec![900.0, 900.0], vec![1100.0, 1050.0]];
+        // no area
+        _labelme.shapes[2].points = vec![vec![10.0, 10.0], vec![10.0, 50.0]];
+        _labelme.shapes.push(_labelme.shapes[3].to_owned());
+
+        let (_changes, _unfixable) = repair_annotation(&mut _labelme, &_json, &RepairFix::all());
+        assert_eq!(_unfixable, None);
+        assert_eq!(_labelme.imagePath, "frame.png");
+        assert_eq!(_labelme.imageWidth, 1024);
+        assert_eq!(
+            _labelme.shapes[0].points,
+            vec![vec![100.0, 200.0], vec![300.0, 400.0]]
+        );
+        assert_eq!(
+            _labelme.shapes[1].points,
+            vec![vec![900.0, 900.0], vec![1024.0, 1024.0]]
+        );
+        assert_eq!(_labelme.shapes.len(), 3);
+        assert_eq!(_labelme.shapes[2], _orig.shapes[3]);
+        let _fixes: Vec<RepairFix> = _changes.iter().map(|c| c.fix).collect();
+        assert_eq!(
+            _fixes,
+            vec![
+                RepairFix::ImagePath,
+                RepairFix::Dimensions,
+                RepairFix::Reorder,
+                RepairFix::Clip,
+                RepairFix::DropDegenerate,
+                RepairFix::Dedupe
+            ]
+        );
+
+        // only the fixes asked for
+        let mut _labelme = _orig.to_owned();
+        _labelme.imagePath = String::from("frame.png");
+        _labelme.shapes[0].points = vec![vec![300.0, 400.0], vec![100.0, 200.0]];
+        let (_changes, _) = repair_annotation(&mut _labelme, &_json, &[RepairFix::Clip]);
+        assert!(_changes.is_empty());
+
+        // without the image the fixes that don't need it still run
+        let mut _labelme = _orig.to_owned();
+        _labelme.imagePath = String::from("gone.png");
+        _labelme.shapes[1].points = vec![vec![900.0, 900.0], vec![1100.0, 1050.0]];
+        let (_changes, _unfixable) =
+            repair_annotation(&mut _labelme, &_tmp.join("gone.json"), &RepairFix::all());
+        assert_eq!(
+            _unfixable,
+            Some(String::from("image \"gone.png\" doesn't exist"))
+        );
+        assert_eq!(
+            _labelme.shapes[1].points,
+            vec![vec![900.0, 900.0], vec![1024.0, 1024.0]]
+        );
+        fs::remove_dir_all(&_tmp).unwrap();
+    }
+
+    #[test]
+    fn repair_folder_dry_run_and_output() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(_tmp.join("src/session")).unwrap();
+        fs::copy("test/test.png", _tmp.join("src/session/test.png")).unwrap();
+        let mut _labelme = read_labels_from_file("test/test.json").unwrap();
+        _labelme.shapes[0].points.reverse();
+        // keys kesa doesn't know about survive the rewrite
+        _labelme.shapes[0]
+            .extra
+            .insert(String::from("description"), "torn corner".into());
+        _labelme
+            .extra
+            .insert(String::from("otherData"), serde_json::json!({"camera": 2}));
+        let _json = _tmp.join("src/session/test.json");
+        fs::write(&_json, serde_json::to_string(&_labelme).unwrap()).unwrap();
+        fs::write(_tmp.join("src/broken.json"), "{").unwrap();
+
+        let mut _options = RepairOptions {
+            fixes: RepairFix::all(),
+            dry_run: true,
+            output: None,
+            discovery: DiscoveryOptions::default(),
+        };
+        let _report = repair_folder(&_tmp.join("src"), &_options).unwrap();
+        assert_eq!((_report.files_checked, _report.files_changed), (2, 1));
+        assert_eq!(_report.skipped.len(), 1);
+        assert_eq!(
+            read_labels_from_file(_json.to_str().unwrap()).unwrap(),
+            _labelme
+        );
+
+        _options.dry_run = false;
+        _options.output = Some(_tmp.join("fixed"));
+        repair_folder(&_tmp.join("src"), &_options).unwrap();
+        assert_eq!(
+            read_labels_from_file(_json.to_str().unwrap()).unwrap(),
+            _labelme
+        );
+        let _fixed =
+            read_labels_from_file(_tmp.join("fixed/session/test.json").to_str().unwrap()).unwrap();
+        assert!(_tmp.join("fixed/session/test.png").exists());
+        assert_ne!(_fixed.shapes[0].points, _labelme.shapes[0].points);
+        assert_eq!(_fixed.shapes[0].extra["description"], "torn corner");
+        assert_eq!(_fixed.extra["otherData"]["camera"], 2);
+        assert!(_tmp.join("fixed/broken.json").exists());
+
+        // images outside the folder are moved next to the json, which the report shows
+        fs::create_dir_all(_tmp.join("images")).unwrap();
+        fs::rename(
+            _tmp.join("src/session/test.png"),
+            _tmp.join("images/test.png"),
+        )
+        .unwrap();
+        _labelme.imagePath = String::from("../../images/test.png");
+        fs::write(&_json, serde_json::to_string(&_labelme).unwrap()).unwrap();
+        _options.dry_run = true;
+        let _report = repair_folder(&_tmp.join("src"), &_options).unwrap();
+        assert!(_report
+            .changes
+            .iter()
+            .any(|c| c.fix == RepairFix::ImagePath && c.after == "test.png"));
+        fs::remove_dir_all(&_tmp).unwrap();
+    }
+}
@@ -0,0 +1,739 @@
+/* reproducible train/val/test splits */
+use anyhow::{bail, Error, Result};
+use clap::{ArgAction, Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const SPLIT_MANIFEST_NAME: &str = "split_manifest.json";
+
+/// size of a batch, `0.7` is a ratio of the dataset, `700` an amount of files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitSize {
+    Ratio(f32),
+    Count(usize),
+}
+
+impl FromStr for SplitSize {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if let Ok(count) = input.parse::<usize>() {
+            return Ok(SplitSize::Count(count));
+        }
+        match input.parse::<f32>() {
+            Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(SplitSize::Ratio(ratio)),
+            _ => bail!(
+                "[error]::split: {:?} is neither a ratio (0.0 - 1.0) nor a file count",
+                input
+            ),
+        }
+    }
+}
+
+impl SplitSize {
+    fn count_of(&self, total: usize) -> usize {
+        match self {
+            SplitSize::Ratio(ratio) => (total as f32 * ratio).round() as usize,
+            SplitSize::Count(count) => *count,
+        }
+    }
+}
+
+/// how files are assigned to the batches
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum SplitStrategy {
+    /// seeded shuffle
+    #[default]
+    Random,
+    /// keeps the instance count of every class proportional
+    /// to the batch sizes (iterative stratification)
+    Stratified,
+}
+
+/// instances of every class in train, val and test
+pub type ClassDistribution = BTreeMap<String, [usize; 3]>;
+
+// split flags shared by `kesa_l2y` and `kesa_split`
+#[derive(Args, Debug, Clone)]
+pub struct SplitArguments {
+    #[arg(long, default_value = "0.7")]
+    /// ratio (0.7) or amount of files (700) in the train batch
+    pub train: SplitSize,
+
+    #[arg(long, default_value = "0.2")]
+    /// ratio or amount of files in the val batch
+    pub val: SplitSize,
+
+    #[arg(long)]
+    /// ratio or amount of files in the test batch,
+    /// defaults to everything left over
+    pub test: Option<SplitSize>,
+
+    #[arg(long, default_value_t = 42)]
+    /// files are shuffled with this seed, same seed same split
+    pub seed: u64,
+
+    #[arg(long, value_enum, default_value_t = SplitStrategy::Random)]
+    pub strategy: SplitStrategy,
+
+    #[arg(long)]
+    /// files whose name gives the same first capture group
+    /// (or match, without a group) stay in the same batch,
+    /// e.g. `^(.*)_frame\d+` for video frames
+    pub group_regex: Option<String>,
+
+    #[arg(long, action=ArgAction::SetTrue)]
+    /// files in the same folder stay in the same batch
+    pub group_by_folder: bool,
+
+    #[arg(long)]
+    /// files with the same value for this labelme flag
+    /// stay in the same batch
+    pub group_flag: Option<String>,
+
+    #[arg(long)]
+    /// cluster file of `kesa_dedup --mode tag` run on the same folder,
+    /// files of a cluster member (same path without extension) stay in its batch
+    pub group_clusters: Option<String>,
+}
+
+/// `path` relative to `root` without its extension, with a `labels`
+/// parent folder read as `images` so yolo txts match their images
+pub fn cluster_key(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path).with_extension("");
+    let relative = match relative.parent() {
+        Some(parent) if parent.file_name().is_some_and(|p| p == "labels") => parent
+            .with_file_name("images")
+            .join(relative.file_name().unwrap_or_default()),
+        _ => relative,
+    };
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// `cluster_key` -> cluster of every image in a `kesa_dedup`
+/// cluster file (cluster -> images relative to the dataset folder)
+pub fn read_dedup_clusters(input: &Path) -> Result<HashMap<String, String>, Error> {
+    let clusters: BTreeMap<String, Vec<String>> = serde_yaml::from_reader(fs::File::open(input)?)?;
+    let mut cluster_of: HashMap<String, String> = HashMap::new();
+    for (cluster, images) in clusters.into_iter() {
+        for image in images.iter() {
+            let key = cluster_key(Path::new(""), Path::new(image));
+            match cluster_of.get(&key) {
+                Some(other) if *other != cluster => bail!(
+                    "[error]::split: {:?} is in clusters {} and {} of {:?}",
+                    key,
+                    other,
+                    cluster,
+                    input
+                ),
+                _ => cluster_of.insert(key, cluster.to_owned()),
+            };
+        }
+    }
+    Ok(cluster_of)
+}
+
+/// files of every batch, in the order they were assigned
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitBatches<T> {
+    pub train: Vec<T>,
+    pub val: Vec<T>,
+    pub test: Vec<T>,
+}
+
+impl SplitArguments {
+    /// amount of files per batch for a dataset of `total` files.
+    ///
+    /// without `--test` the test batch gets the rest, with ratios
+    /// adding up to 1 rounding leftovers go to train,
+    /// otherwise files not asked for are left out
+    pub fn batch_sizes(&self, total: usize) -> Result<(usize, usize, usize), Error> {
+        let train = self.train.count_of(total);
+        let val = self.val.count_of(total);
+        if train + val > total {
+            bail!(
+                "[error]::split: train ({}) + val ({}) is more than the {} files found",
+                train,
+                val,
+                total
+            );
+        }
+        let test = match self.test {
+            None => return Ok((train, val, total - train - val)),
+            Some(test) => test.count_of(total),
+        };
+        if train + val + test > total {
+            bail!(
+                "[error]::split: train ({}) + val ({}) + test ({}) is more than the {} files found",
+                train,
+                val,
+                test,
+                total
+            );
+        }
+        let ratio_sum = match (self.train, self.val, self.test) {
+            (SplitSize::Ratio(tr), SplitSize::Ratio(va), Some(SplitSize::Ratio(te))) => {
+                Some(tr + va + te)
+            }
+            _ => None,
+        };
+        match ratio_sum {
+            Some(sum) if (sum - 1.0).abs() < 1e-3 => Ok((total - val - test, val, test)),
+            _ => Ok((train, val, test)),
+        }
+    }
+
+    /// sorts the files first so the filesystem order doesn't matter,
+    /// then shuffles them with the seed and cuts them into batches
+    pub fn split<T: Clone + Ord>(&self, items: &[T]) -> Result<SplitBatches<T>, Error> {
+        let (train, val, test) = self.batch_sizes(items.len())?;
+        let mut shuffled = items.to_vec();
+        shuffled.sort();
+        shuffled.shuffle(&mut StdRng::seed_from_u64(self.seed));
+        Ok(SplitBatches {
+            train: shuffled[..train].to_vec(),
+            val: shuffled[train..train + val].to_vec(),
+            test: shuffled[train + val..train + val + test].to_vec(),
+        })
+    }
+
+    /// group key of every file, `None` if grouping is off
+    /// or the file doesn't have every configured key part.
+    ///
+    /// `read_flags` gives the labelme `flags` of a file and
+    /// is only called with `--group-flag`, `root` is the dataset folder
+    /// the `--group-clusters` paths are relative to
+    pub fn group_keys<F>(
+        &self,
+        root: &Path,
+        files: &[PathBuf],
+        read_flags: F,
+    ) -> Result<Vec<Option<String>>, Error>
+    where
+        F: Fn(&Path) -> Result<HashMap<String, String>, Error>,
+    {
+        let group_regex = match &self.group_regex {
+            Some(pattern) => Some(Regex::new(pattern)?),
+            None => None,
+        };
+        let cluster_of = match &self.group_clusters {
+            Some(clusters_file) => Some(read_dedup_clusters(Path::new(clusters_file))?),
+            None => None,
+        };
+        let mut all_keys: Vec<Option<String>> = vec![];
+        for file in files.iter() {
+            let mut parts: Vec<Option<String>> = vec![];
+            if let Some(group_regex) = &group_regex {
+                let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+                // first capture group, or the whole match without one
+                parts.push(group_regex.captures(&file_name).and_then(|captures| {
+                    captures
+                        .get(1)
+                        .or_else(|| captures.get(0))
+                        .map(|m| m.as_str().to_string())
+                }));
+            }
+            if self.group_by_folder {
+                parts.push(file.parent().map(|p| p.to_string_lossy().to_string()));
+            }
+            if let Some(group_flag) = &self.group_flag {
+                parts.push(read_flags(file)?.get(group_flag).cloned());
+            }
+            if let Some(cluster_of) = &cluster_of {
+                parts.push(cluster_of.get(&cluster_key(root, file)).cloned());
+            }
+            all_keys.push(match parts.is_empty() {
+                true => None,
+                false => parts
+                    .into_iter()
+                    .collect::<Option<Vec<String>>>()
+                    .map(|parts| parts.join("/")),
+            });
+        }
+        Ok(all_keys)
+    }
+
+    /// like `split`, `labels` are the class labels of every item
+    /// (one entry per instance) and are used by the stratified strategy
+    /// and for the class distribution of the batches.
+    ///
+    /// items sharing a group key always land in the same batch,
+    /// items without one are a group of their own
+    pub fn split_labeled<T: Clone + Ord>(
+        &self,
+        items: &[T],
+        labels: &[Vec<String>],
+        groups: &[Option<String>],
+    ) -> Result<(SplitBatches<T>, ClassDistribution), Error> {
+        if items.len() != labels.len() || items.len() != groups.len() {
+            bail!(
+                "[error]::split: got {} items but {} label lists and {} group keys",
+                items.len(),
+                labels.len(),
+                groups.len()
+            );
+        }
+        let labels_of: BTreeMap<&T, &Vec<String>> = items.iter().zip(labels.iter()).collect();
+
+        // every unit of assignment, sorted by their first item
+        let mut grouped: BTreeMap<&String, Vec<T>> = BTreeMap::new();
+        let mut units: Vec<Vec<T>> = vec![];
+        for (item, group) in items.iter().zip(groups.iter()) {
+            match group {
+                Some(key) => grouped.entry(key).or_default().push(item.to_owned()),
+                None => units.push(vec![item.to_owned()]),
+            }
+        }
+        if !grouped.is_empty() {
+            println!(
+                "[info]::split: {} files in {} groups",
+                grouped.values().map(|g| g.len()).sum::<usize>(),
+                grouped.len()
+            );
+        }
+        units.extend(grouped.into_values());
+        for unit in units.iter_mut() {
+            unit.sort();
+        }
+        units.sort();
+
+        let batches = match self.strategy {
+            SplitStrategy::Random => self.split_units(units, items.len())?,
+            SplitStrategy::Stratified => self.split_stratified(units, items.len(), &labels_of)?,
+        };
+        let mut distribution = ClassDistribution::new();
+        for (batch_idx, batch) in [&batches.train, &batches.val, &batches.test]
+            .iter()
+            .enumerate()
+        {
+            for item in batch.iter() {
+                for label in labels_of[item].iter() {
+                    distribution.entry(label.to_owned()).or_insert([0; 3])[batch_idx] += 1;
+                }
+            }
+        }
+        Ok((batches, distribution))
+    }
+
+    /// seeded shuffle of the units, then cut into batches like `split`,
+    /// a group crossing a cut goes to the batch it starts in
+    fn split_units<T: Clone + Ord>(
+        &self,
+        mut units: Vec<Vec<T>>,
+        total: usize,
+    ) -> Result<SplitBatches<T>, Error> {
+        let (train, val, test) = self.batch_sizes(total)?;
+        let cuts = [train, train + val, train + val + test];
+        units.shuffle(&mut StdRng::seed_from_u64(self.seed));
+        let mut assigned: Vec<Vec<T>> = vec![vec![]; 4];
+        let mut seen = 0;
+        for unit in units.into_iter() {
+            let batch = cuts.iter().position(|cut| seen < *cut).unwrap_or(3);
+            seen += unit.len();
+            assigned[batch].extend(unit);
+        }
+        Ok(SplitBatches {
+            train: assigned[0].to_owned(),
+            val: assigned[1].to_owned(),
+            test: assigned[2].to_owned(),
+        })
+    }
+
+    /// iterative stratification (sechidis et al.) on class instance counts.
+    ///
+    /// the class with the fewest instances left is handled first,
+    /// every unit with that class goes to the batch that still wants the
+    /// most instances of it. units without labels, or files left out
+    /// by the batch sizes, fill whatever room is left
+    fn split_stratified<T: Clone + Ord>(
+        &self,
+        mut units: Vec<Vec<T>>,
+        total: usize,
+        labels_of: &BTreeMap<&T, &Vec<String>>,
+    ) -> Result<SplitBatches<T>, Error> {
+        let (train, val, test) = self.batch_sizes(total)?;
+        // the 4th batch holds files that aren't in any batch
+        let mut wanted_items: Vec<f32> = vec![
+            train as f32,
+            val as f32,
+            test as f32,
+            (total - train - val - test) as f32,
+        ];
+        let ratios: Vec<f32> = wanted_items
+            .iter()
+            .map(|wanted| wanted / total.max(1) as f32)
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        units.shuffle(&mut rng);
+        let unit_labels = |unit: &Vec<T>| -> Vec<String> {
+            unit.iter()
+                .flat_map(|item| labels_of[item].iter().cloned())
+                .collect()
+        };
+        let mut remaining: Vec<(Vec<T>, Vec<String>)> = units
+            .into_iter()
+            .map(|unit| {
+                let labels = unit_labels(&unit);
+                (unit, labels)
+            })
+            .collect();
+
+        let mut instances_left: HashMap<String, usize> = HashMap::new();
+        for (_, labels) in remaining.iter() {
+            for label in labels.iter() {
+                *instances_left.entry(label.to_owned()).or_insert(0) += 1;
+            }
+        }
+        let mut wanted_instances: HashMap<String, Vec<f32>> = instances_left
+            .iter()
+            .map(|(label, count)| {
+                (
+                    label.to_owned(),
+                    ratios.iter().map(|r| r * *count as f32).collect(),
+                )
+            })
+            .collect();
+
+        let mut assigned: Vec<Vec<T>> = vec![vec![]; 4];
+        loop {
+            // rarest class first, ties by name so the result doesn't depend on hashing
+            let rarest = instances_left
+                .iter()
+                .filter(|(_, count)| **count > 0)
+                .min_by(|a, b| a.1.cmp(b.1).then(a.0.cmp(b.0)))
+                .map(|(label, _)| label.to_owned());
+            let rarest = match rarest {
+                Some(label) => label,
+                None => break,
+            };
+            let (with_label, without_label): (Vec<_>, Vec<_>) = remaining
+                .into_iter()
+                .partition(|(_, labels)| labels.contains(&rarest));
+            remaining = without_label;
+            for (unit, labels) in with_label.into_iter() {
+                let batch = pick_batch(
+                    &wanted_items,
+                    &wanted_instances[&rarest],
+                    unit.len(),
+                    &mut rng,
+                );
+                wanted_items[batch] -= unit.len() as f32;
+                for label in labels.iter() {
+                    wanted_instances.get_mut(label).unwrap()[batch] -= 1.0;
+                    *instances_left.get_mut(label).unwrap() -= 1;
+                }
+                assigned[batch].extend(unit);
+            }
+        }
+        for (unit, _) in remaining.into_iter() {
+            let batch = pick_batch(&wanted_items, &wanted_items, unit.len(), &mut rng);
+            wanted_items[batch] -= unit.len() as f32;
+            assigned[batch].extend(unit);
+        }
+
+        Ok(SplitBatches {
+            train: assigned[0].to_owned(),
+            val: assigned[1].to_owned(),
+            test: assigned[2].to_owned(),
+        })
+    }
+}
+
+/// batch with room for `size` files that wants the most, ties go to
+/// the batch with the most room left and then to chance.
+/// big groups that fit nowhere go where there's any room
+fn pick_batch(wanted_items: &[f32], wanted: &[f32], size: usize, rng: &mut StdRng) -> usize {
+    let with_room = |room: f32| -> Vec<usize> {
+        (0..wanted_items.len())
+            .filter(|idx| wanted_items[*idx] >= room)
+            .collect()
+    };
+    let mut open = with_room(size as f32);
+    if open.is_empty() {
+        open = with_room(1.0);
+    }
+    if open.is_empty() {
+        open = (0..wanted_items.len()).collect();
+    }
+    let best = |candidates: Vec<usize>, scores: &[f32]| -> Vec<usize> {
+        let max = candidates
+            .iter()
+            .map(|idx| scores[*idx])
+            .fold(f32::MIN, f32::max);
+        candidates
+            .into_iter()
+            .filter(|idx| scores[*idx] == max)
+            .collect()
+    };
+    let candidates = best(best(open, wanted), wanted_items);
+    match candidates.len() {
+        0 => 0,
+        1 => candidates[0],
+        n => candidates[rng.gen_range(0..n)],
+    }
+}
+
+/// prints how many instances of every class ended up in each batch
+pub fn print_class_distribution(distribution: &ClassDistribution) {
+    println!(
+        "[info]::split: class distribution\n{:<24} {:>8} {:>8} {:>8}",
+        "class", "train", "val", "test"
+    );
+    for (label, counts) in distribution.iter() {
+        let total: usize = counts.iter().sum();
+        let percent = |count: usize| match total {
+            0 => 0.0,
+            _ => 100.0 * count as f32 / total as f32,
+        };
+        println!(
+            "{:<24} {:>8} {:>8} {:>8}    ({:.0}% / {:.0}% / {:.0}%)",
+            label,
+            counts[0],
+            counts[1],
+            counts[2],
+            percent(counts[0]),
+            percent(counts[1]),
+            percent(counts[2])
+        );
+    }
+}
+
+/// where every file of a split went, written as `split_manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitManifest {
+    pub seed: u64,
+    #[serde(default)]
+    pub class_distribution: ClassDistribution,
+    pub train: Vec<SplitManifestEntry>,
+    pub val: Vec<SplitManifestEntry>,
+    pub test: Vec<SplitManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitManifestEntry {
+    /// the source annotation (or label) file
+    pub source: String,
+    pub image: String,
+    pub label: String,
+}
+
+pub fn write_split_manifest(manifest: &SplitManifest, folder: &Path) -> Result<(), Error> {
+    let mut manifest_file = fs::File::create(folder.join(SPLIT_MANIFEST_NAME))?;
+    serde_json::to_writer_pretty(&mut manifest_file, manifest)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_split {
+    use crate::split::*;
+    use uuid::Uuid;
+
+    fn split_args(train: &str, val: &str, test: Option<&str>, seed: u64) -> SplitArguments {
+        SplitArguments {
+            train: train.parse().unwrap(),
+            val: val.parse().unwrap(),
+            test: test.map(|t| t.parse().unwrap()),
+            seed,
+            strategy: SplitStrategy::Random,
+            group_regex: None,
+            group_by_folder: false,
+            group_flag: None,
+            group_clusters: None,
+        }
+    }
+
+    #[test]
+    fn split_sizes() {
+        assert_eq!("0.7".parse::<SplitSize>().unwrap(), SplitSize::Ratio(0.7));
+        assert_eq!("700".parse::<SplitSize>().unwrap(), SplitSize::Count(700));
+        assert!("1.5".parse::<SplitSize>().is_err());
+        assert!("abc".parse::<SplitSize>().is_err());
+
+        assert_eq!(
+            split_args("0.7", "0.2", None, 0).batch_sizes(10).unwrap(),
+            (7, 2, 1)
+        );
+        assert_eq!(
+            split_args("0.6", "0.2", Some("0.2"), 0)
+                .batch_sizes(11)
+                .unwrap(),
+            (7, 2, 2)
+        );
+        assert_eq!(
+            split_args("5", "2", Some("1"), 0).batch_sizes(10).unwrap(),
+            (5, 2, 1)
+        );
+        assert_eq!(
+            split_args("5", "0.5", None, 0).batch_sizes(10).unwrap(),
+            (5, 5, 0)
+        );
+        assert!(split_args("8", "3", None, 0).batch_sizes(10).is_err());
+        assert!(split_args("0.7", "0.2", Some("0.2"), 0)
+            .batch_sizes(10)
+            .is_err());
+    }
+
+    #[test]
+    fn split_is_reproducible() {
+        let _items: Vec<String> = (0..50).map(|i| format!("{:02}.json", i)).collect();
+        let mut _reversed = _items.to_owned();
+        _reversed.reverse();
+        let _args = split_args("0.7", "0.2", None, 7);
+        let _batches = _args.split(&_items).unwrap();
+        assert_eq!(_batches, _args.split(&_reversed).unwrap());
+        assert_eq!(
+            (
+                _batches.train.len(),
+                _batches.val.len(),
+                _batches.test.len()
+            ),
+            (35, 10, 5)
+        );
+        assert_ne!(_batches.train, _items[..35].to_vec());
+        assert_ne!(
+            _batches,
+            split_args("0.7", "0.2", None, 8).split(&_items).unwrap()
+        );
+    }
+
+    #[test]
+    fn stratified_split_keeps_rare_classes() {
+        // 100 images of a common class, 10 with a rare one
+        let _items: Vec<String> = (0..110).map(|i| format!("{:03}.json", i)).collect();
+        let _labels: Vec<Vec<String>> = (0..110)
+            .map(|i| match i {
+                0..=99 => vec![String::from("common"), String::from("common")],
+                _ => vec![String::from("rare"), String::from("common")],
+            })
+            .collect();
+        let mut _args = split_args("0.6", "0.2", Some("0.2"), 3);
+        _args.strategy = SplitStrategy::Stratified;
+        let (_batches, _distribution) = _args
+            .split_labeled(&_items, &_labels, &vec![None; 110])
+            .unwrap();
+        assert_eq!(
+            (
+                _batches.train.len(),
+                _batches.val.len(),
+                _batches.test.len()
+            ),
+            (66, 22, 22)
+        );
+        assert_eq!(_distribution["rare"], [6, 2, 2]);
+        assert_eq!(_distribution["common"].iter().sum::<usize>(), 210);
+        assert_eq!(
+            _args
+                .split_labeled(&_items, &_labels, &vec![None; 110])
+                .unwrap()
+                .0,
+            _batches
+        );
+
+        // left out files don't take part
+        let mut _args = split_args("10", "5", Some("5"), 3);
+        _args.strategy = SplitStrategy::Stratified;
+        let (_batches, _distribution) = _args
+            .split_labeled(&_items, &_labels, &vec![None; 110])
+            .unwrap();
+        assert_eq!(
+            _batches.train.len() + _batches.val.len() + _batches.test.len(),
+            20
+        );
+    }
+
+    #[test]
+    fn groups_stay_together() {
+        // 20 videos of 5 frames each
+        let _items: Vec<PathBuf> = (0..100)
+            .map(|i| PathBuf::from(format!("cap/video{:02}_frame{}.json", i / 5, i % 5)))
+            .collect();
+        let _labels: Vec<Vec<String>> = (0..100).map(|i| vec![format!("class{}", i % 3)]).collect();
+        for _strategy in [SplitStrategy::Random, SplitStrategy::Stratified] {
+            let mut _args = split_args("0.6", "0.2", None, 5);
+            _args.strategy = _strategy;
+            _args.group_regex = Some(String::from(r"^(video\d+)_"));
+            let _groups = _args
+                .group_keys(Path::new("cap"), &_items, |_| Ok(HashMap::new()))
+                .unwrap();
+            assert_eq!(_groups[7], Some(String::from("video01")));
+            let (_batches, _) = _args.split_labeled(&_items, &_labels, &_groups).unwrap();
+            assert_eq!(
+                (
+                    _batches.train.len(),
+                    _batches.val.len(),
+                    _batches.test.len()
+                ),
+                (60, 20, 20)
+            );
+            for _batch in [&_batches.train, &_batches.val, &_batches.test] {
+                for _item in _batch.iter() {
+                    let _video = &_item.to_string_lossy()[..11];
+                    assert_eq!(
+                        _batch
+                            .iter()
+                            .filter(|i| i.to_string_lossy().starts_with(_video))
+                            .count(),
+                        5
+                    );
+                }
+            }
+        }
+
+        let mut _args = split_args("0.6", "0.2", None, 5);
+        _args.group_flag = Some(String::from("scene"));
+        _args.group_by_folder = true;
+        let _groups = _args
+            .group_keys(Path::new("cap"), &_items[..2], |file| {
+                let mut _flags = HashMap::new();
+                if file.to_string_lossy().contains("frame0") {
+                    _flags.insert(String::from("scene"), String::from("kitchen"));
+                }
+                Ok(_flags)
+            })
+            .unwrap();
+        assert_eq!(_groups, vec![Some(String::from("cap/kitchen")), None]);
+
+        let _clusters = std::env::temp_dir().join(format!("{}.yaml", Uuid::new_v4()));
+        fs::write(
+            &_clusters,
+            "dup_00000:\n- session1/images/img001.png\n- session1/images/img002.png\n\
+             dup_00001:\n- session2/img001.jpg\n- session2/img009.jpg\n",
+        )
+        .unwrap();
+        let mut _args = split_args("0.6", "0.2", None, 5);
+        _args.group_clusters = Some(_clusters.to_string_lossy().to_string());
+        // same names in different folders, yolo txts match their images
+        let _files: Vec<PathBuf> = [
+            "data/session1/labels/img001.txt",
+            "data/session2/img001.txt",
+            "data/session3/img001.txt",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let _groups = _args
+            .group_keys(Path::new("data"), &_files, |_| Ok(HashMap::new()))
+            .unwrap();
+        assert_eq!(
+            _groups,
+            vec![
+                Some(String::from("dup_00000")),
+                Some(String::from("dup_00001")),
+                None
+            ]
+        );
+        fs::remove_file(_clusters).unwrap();
+    }
+}
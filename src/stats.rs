@@ -0,0 +1,223 @@
+/* dataset statistics, the json summary is meant to be diffed between dataset versions */
+use crate::dataset::Dataset;
+use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// cells per side of the box center heatmap
+pub const HEATMAP_CELLS: usize = 10;
+
+/// lower bounds of the box width / height bins (relative to the image)
+pub const RELATIVE_SIZE_BINS: [f32; 10] = [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+
+/// lower bounds of the aspect ratio (w / h) bins
+pub const ASPECT_RATIO_BINS: [f32; 9] = [0.0, 0.25, 0.5, 0.75, 1.0, 1.33, 2.0, 3.0, 4.0];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassStats {
+    pub instances: usize,
+    /// images with at least one instance
+    pub images: usize,
+}
+
+/// `counts[i]` holds values from `lower_bounds[i]` up to the next bound,
+/// the last bin is open ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBins {
+    pub lower_bounds: Vec<f32>,
+    pub counts: Vec<usize>,
+}
+
+impl HistogramBins {
+    pub fn new(lower_bounds: &[f32]) -> HistogramBins {
+        HistogramBins {
+            lower_bounds: lower_bounds.to_vec(),
+            counts: vec![0; lower_bounds.len()],
+        }
+    }
+
+    pub fn add(&mut self, value: f32) {
+        let bin = self
+            .lower_bounds
+            .iter()
+            .rposition(|bound| value >= *bound)
+            .unwrap_or(0);
+        self.counts[bin] += 1;
+    }
+
+    /// `0.1-0.2` style names of the bins, for chart axes
+    pub fn bin_names(&self) -> Vec<String> {
+        self.lower_bounds
+            .iter()
+            .enumerate()
+            .map(|(idx, bound)| match self.lower_bounds.get(idx + 1) {
+                Some(next) => format!("{}-{}", bound, next),
+                None => format!("{}+", bound),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SplitStats {
+    pub images: usize,
+    pub instances: usize,
+    pub classes: BTreeMap<String, ClassStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetStats {
+    pub images: usize,
+    pub instances: usize,
+    pub images_without_labels: usize,
+    pub classes: BTreeMap<String, ClassStats>,
+    /// amount of images with n shapes
+    pub objects_per_image: BTreeMap<usize, usize>,
+    /// amount of images per `WxH`
+    pub resolutions: BTreeMap<String, usize>,
+    /// box width relative to the image width
+    pub box_width: HistogramBins,
+    /// box height relative to the image height
+    pub box_height: HistogramBins,
+    /// box width / height in pixels
+    pub aspect_ratio: HistogramBins,
+    /// box centers on a `HEATMAP_CELLS` grid, `[row][column]` from the top left
+    pub center_heatmap: Vec<Vec<usize>>,
+    /// only for datasets that are split into train/val/test
+    pub splits: BTreeMap<String, SplitStats>,
+}
+
+/// counts everything in a dataset, box statistics
+/// only use shapes with an area
+pub fn compute_stats(dataset: &Dataset) -> Result<DatasetStats, Error> {
+    let mut stats = DatasetStats {
+        images: dataset.items.len(),
+        instances: 0,
+        images_without_labels: 0,
+        classes: BTreeMap::new(),
+        objects_per_image: BTreeMap::new(),
+        resolutions: BTreeMap::new(),
+        box_width: HistogramBins::new(&RELATIVE_SIZE_BINS),
+        box_height: HistogramBins::new(&RELATIVE_SIZE_BINS),
+        aspect_ratio: HistogramBins::new(&ASPECT_RATIO_BINS),
+        center_heatmap: vec![vec![0; HEATMAP_CELLS]; HEATMAP_CELLS],
+        splits: BTreeMap::new(),
+    };
+    for class in dataset.all_classes().iter() {
+        stats
+            .classes
+            .insert(class.to_owned(), ClassStats::default());
+    }
+
+    for item in dataset.items.iter() {
+        stats.instances += item.shapes.len();
+        if item.shapes.is_empty() {
+            stats.images_without_labels += 1;
+        }
+        *stats
+            .objects_per_image
+            .entry(item.shapes.len())
+            .or_insert(0) += 1;
+        *stats
+            .resolutions
+            .entry(format!("{}x{}", item.width, item.height))
+            .or_insert(0) += 1;
+
+        let mut split_stats = item
+            .split
+            .as_ref()
+            .map(|split| stats.splits.entry(split.to_owned()).or_default());
+        if let Some(split_stats) = split_stats.as_mut() {
+            split_stats.images += 1;
+            split_stats.instances += item.shapes.len();
+        }
+        let item_classes: HashSet<&String> = item.shapes.iter().map(|s| &s.label).collect();
+        for shape in item.shapes.iter() {
+            stats
+                .classes
+                .entry(shape.label.to_owned())
+                .or_default()
+                .instances += 1;
+            if let Some(split_stats) = split_stats.as_mut() {
+                split_stats
+                    .classes
+                    .entry(shape.label.to_owned())
+                    .or_default()
+                    .instances += 1;
+            }
+        }
+        for class in item_classes.into_iter() {
+            stats.classes.entry(class.to_owned()).or_default().images += 1;
+            if let Some(split_stats) = split_stats.as_mut() {
+                split_stats
+                    .classes
+                    .entry(class.to_owned())
+                    .or_default()
+                    .images += 1;
+            }
+        }
+
+        let (w, h) = (item.width.max(1) as f32, item.height.max(1) as f32);
+        for shape in item.shapes.iter().filter(|s| s.shape_type.has_area()) {
+            let bbox = match shape.bbox() {
+                Ok(bbox) => bbox,
+                Err(_) => continue,
+            };
+            let (box_w, box_h) = (bbox.x2 - bbox.x1, bbox.y2 - bbox.y1);
+            stats.box_width.add(box_w / w);
+            stats.box_height.add(box_h / h);
+            if box_h > 0.0 {
+                stats.aspect_ratio.add(box_w / box_h);
+            }
+            let cell = |center: f32, size: f32| -> usize {
+                ((center / size * HEATMAP_CELLS as f32).max(0.0) as usize).min(HEATMAP_CELLS - 1)
+            };
+            let column = cell((bbox.x1 + bbox.x2) / 2.0, w);
+            let row = cell((bbox.y1 + bbox.y2) / 2.0, h);
+            stats.center_heatmap[row][column] += 1;
+        }
+    }
+    Ok(stats)
+}
+
+/// writes the summary as pretty json
+pub fn write_stats_to_json(stats: &DatasetStats, filename: &Path) -> Result<(), Error> {
+    fs::write(filename, serde_json::to_string_pretty(stats)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_stats {
+    use crate::formats::annotation_format::get_format;
+    use crate::stats::*;
+
+    #[test]
+    fn stats_of_test_folder() {
+        let mut _dataset = get_format("labelme")
+            .unwrap()
+            .read_dataset(Path::new("test"))
+            .unwrap();
+        let _stats = compute_stats(&_dataset).unwrap();
+        assert_eq!((_stats.images, _stats.instances), (1, 4));
+        assert_eq!(_stats.resolutions["1024x1024"], 1);
+        assert_eq!(_stats.objects_per_image[&4], 1);
+        assert_eq!(_stats.classes["eye"].images, 1);
+        assert_eq!(
+            _stats.classes.values().map(|c| c.instances).sum::<usize>(),
+            4
+        );
+        assert_eq!(_stats.box_width.counts.iter().sum::<usize>(), 4);
+        assert_eq!(_stats.center_heatmap.iter().flatten().sum::<usize>(), 4);
+        assert!(_stats.splits.is_empty());
+
+        _dataset.items[0].split = Some(String::from("train"));
+        let _stats = compute_stats(&_dataset).unwrap();
+        assert_eq!(_stats.splits["train"].instances, 4);
+
+        let _bins = HistogramBins::new(&ASPECT_RATIO_BINS);
+        assert_eq!(_bins.bin_names()[0], "0-0.25");
+        assert_eq!(_bins.bin_names()[8], "4+");
+    }
+}
@@ -0,0 +1,512 @@
+/* dataset checks run before training */
+use crate::fileutils::{
+    find_files, get_txt_from_image, has_extension, read_yolo_from_txt, DiscoveryOptions,
+    IMAGE_EXTENSIONS,
+};
+use crate::label::{polygon_area, LabelmeAnnotation, Shape, YoloLabel};
+use anyhow::{Error, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// json (or yolo txt) that can't be parsed
+    MalformedLabel,
+    /// fewer points than the shape type needs, or points without x/y
+    TooFewPoints,
+    /// zero or negative area
+    DegenerateShape,
+    /// points outside `imageWidth`/`imageHeight` (or 0..1 for yolo)
+    OutOfBounds,
+    /// `imageWidth`/`imageHeight` don't match the image file
+    DimensionMismatch,
+    /// `imagePath` points to a file that doesn't exist
+    MissingImage,
+    /// `imagePath` points to a file with an extension kesa doesn't read
+    UnsupportedImage,
+    /// image file that can't be decoded
+    UnreadableImage,
+    /// image without any label file
+    OrphanImage,
+    /// yolo txt without an image
+    OrphanLabel,
+    /// label that isn't in the class list
+    UnknownClass,
+    /// same label, type and points twice in one file
+    DuplicateShape,
+    /// shape type kesa doesn't handle, exports skip it
+    UnsupportedShape,
+}
+
+impl IssueKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            IssueKind::OutOfBounds
+            | IssueKind::OrphanImage
+            | IssueKind::DuplicateShape
+            | IssueKind::UnsupportedShape => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    pub severity: Severity,
+    pub file: String,
+    /// index into `shapes` (or the line of a yolo txt)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shape: Option<usize>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(kind: IssueKind, file: &Path, shape: Option<usize>, message: String) -> Self {
+        ValidationIssue {
+            kind,
+            severity: kind.severity(),
+            file: file.to_string_lossy().to_string(),
+            shape,
+            message,
+        }
+    }
+}
+
+/// everything found in a dataset, serialized as the `--json` output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationReport {
+    pub folder: String,
+    pub labels_checked: usize,
+    pub images_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub counts: BTreeMap<IssueKind, usize>,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// true if there are errors, or warnings when `strict`
+    pub fn failed(&self, strict: bool) -> bool {
+        self.errors > 0 || (strict && self.warnings > 0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidateOptions {
+    /// known class names, class names aren't checked if `None`
+    pub classes: Option<Vec<String>>,
+    /// open every image to compare its size with the label
+    pub check_image_size: bool,
+    pub discovery: DiscoveryOptions,
+}
+
+/// checks every labelme json and yolo txt in a folder,
+/// problems are collected instead of stopping at the first one
+pub fn validate_folder(
+    folder: &Path,
+    options: &ValidateOptions,
+) -> Result<ValidationReport, Error> {
+    let all_json = find_files(folder, &["json"], &options.discovery)?;
+    let all_txt: Vec<PathBuf> = find_files(folder, &["txt"], &options.discovery)?
+        .into_iter()
+        // class lists some tools write next to the labels
+        .filter(|txt| txt.file_name().unwrap_or_default() != "classes.txt")
+        .collect();
+    let all_images = find_files(folder, &IMAGE_EXTENSIONS, &options.discovery)?;
+
+    let json_results: Vec<(Vec<ValidationIssue>, Option<PathBuf>)> = all_json
+        .par_iter()
+        .map(|json| validate_labelme_file(json, options))
+        .collect();
+    let mut issues: Vec<ValidationIssue> = vec![];
+    let mut labelled_images: HashSet<PathBuf> = HashSet::new();
+    for (json_issues, image) in json_results.into_iter() {
+        issues.extend(json_issues);
+        if let Some(image) = image {
+            labelled_images.insert(image);
+        }
+    }
+
+    // yolo txts pair with images by name, `labels/a.txt` with `images/a.jpg`
+    let txt_of_images: HashSet<PathBuf> = all_images
+        .iter()
+        .flat_map(|image| [get_txt_from_image(image), image.with_extension("txt")])
+        .collect();
+    for txt in all_txt.iter() {
+        if !txt_of_images.contains(txt) {
+            issues.push(ValidationIssue::new(
+                IssueKind::OrphanLabel,
+                txt,
+                None,
+                String::from("no image with the same name"),
+            ));
+            continue;
+        }
+        issues.extend(validate_yolo_file(txt, options));
+    }
+    for image in all_images.iter() {
+        let has_label = labelled_images.contains(&normalize(image))
+            || get_txt_from_image(image).exists()
+            || image.with_extension("txt").exists();
+        if !has_label {
+            issues.push(ValidationIssue::new(
+                IssueKind::OrphanImage,
+                image,
+                None,
+                String::from("no labelme json or yolo txt for this image"),
+            ));
+        }
+    }
+
+    issues.sort_by(|a, b| {
+        a.file
+            .cmp(&b.file)
+            .then(a.shape.cmp(&b.shape))
+            .then(a.kind.cmp(&b.kind))
+    });
+    let mut counts: BTreeMap<IssueKind, usize> = BTreeMap::new();
+    for issue in issues.iter() {
+        *counts.entry(issue.kind).or_insert(0) += 1;
+    }
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    Ok(ValidationReport {
+        folder: folder.to_string_lossy().to_string(),
+        labels_checked: all_json.len() + all_txt.len(),
+        images_checked: all_images.len(),
+        errors,
+        warnings: issues.len() - errors,
+        counts,
+        issues,
+    })
+}
+
+/// `a/./b.png` and `a/b.png` are the same image
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// issues of a labelme json and the image it refers to, if it exists
+fn validate_labelme_file(
+    json: &Path,
+    options: &ValidateOptions,
+) -> (Vec<ValidationIssue>, Option<PathBuf>) {
+    let mut issues: Vec<ValidationIssue> = vec![];
+    let labelme: LabelmeAnnotation = match fs::read_to_string(json)
+        .map_err(Error::from)
+        .and_then(|json_str| Ok(serde_json::from_str(&json_str)?))
+    {
+        Ok(labelme) => labelme,
+        Err(e) => {
+            issues.push(ValidationIssue::new(
+                IssueKind::MalformedLabel,
+                json,
+                None,
+                format!("not a labelme json: {}", e),
+            ));
+            return (issues, None);
+        }
+    };
+
+    let image = json
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&labelme.imagePath);
+    let image = match (image.is_file(), has_extension(&image, &IMAGE_EXTENSIONS)) {
+        (true, true) => Some(normalize(&image)),
+        (true, false) => {
+            issues.push(ValidationIssue::new(
+                IssueKind::UnsupportedImage,
+                json,
+                None,
+                format!(
+                    "imagePath {:?} has an unsupported image extension",
+                    &labelme.imagePath
+                ),
+            ));
+            None
+        }
+        (false, _) => {
+            issues.push(ValidationIssue::new(
+                IssueKind::MissingImage,
+                json,
+                None,
+                format!("imagePath {:?} doesn't exist", &labelme.imagePath),
+            ));
+            None
+        }
+    };
+    if let (Some(image), true) = (&image, options.check_image_size) {
+        match image::image_dimensions(image) {
+            Ok((w, h)) if w as i64 != labelme.imageWidth || h as i64 != labelme.imageHeight => {
+                issues.push(ValidationIssue::new(
+                    IssueKind::DimensionMismatch,
+                    json,
+                    None,
+                    format!(
+                        "label says {}x{}, image is {}x{}",
+                        labelme.imageWidth, labelme.imageHeight, w, h
+                    ),
+                ))
+            }
+            Ok(_) => {}
+            Err(e) => issues.push(ValidationIssue::new(
+                IssueKind::UnreadableImage,
+                json,
+                None,
+                format!("cannot read {:?}: {}", image, e),
+            )),
+        }
+    }
+
+    let (w, h) = (labelme.imageWidth as f32, labelme.imageHeight as f32);
+    let mut seen: Vec<&Shape> = vec![];
+    for (idx, shape) in labelme.shapes.iter().enumerate() {
+        if let Some(issue) = check_class(&shape.label, options) {
+            issues.push(ValidationIssue::new(
+                IssueKind::UnknownClass,
+                json,
+                Some(idx),
+                issue,
+            ));
+        }
+        if seen.contains(&shape) {
+            issues.push(ValidationIssue::new(
+                IssueKind::DuplicateShape,
+                json,
+                Some(idx),
+                format!("{:?} is in the file twice", &shape.label),
+            ));
+        }
+        seen.push(shape);
+
+        let min_points = match shape.shape_type.min_points() {
+            Some(min_points) => min_points,
+            None => {
+                issues.push(ValidationIssue::new(
+                    IssueKind::UnsupportedShape,
+                    json,
+                    Some(idx),
+                    format!(
+                        "{:?} {:?} isn't supported, exports skip it",
+                        shape.shape_type, &shape.label
+                    ),
+                ));
+                continue;
+            }
+        };
+        if shape.points.len() < min_points || shape.points.iter().any(|p| p.len() < 2) {
+            issues.push(ValidationIssue::new(
+                IssueKind::TooFewPoints,
+                json,
+                Some(idx),
+                format!(
+                    "{:?} {:?} needs {} points with x and y, got {:?}",
+                    shape.shape_type, &shape.label, min_points, &shape.points
+                ),
+            ));
+            continue;
+        }
+        if shape.shape_type.has_area() {
+            let degenerate = match shape.bbox() {
+                Ok(bbox) => {
+                    bbox.x2 - bbox.x1 <= 0.0
+                        || bbox.y2 - bbox.y1 <= 0.0
+                        || polygon_area(&shape.polygon().unwrap_or_default()) <= 0.0
+                }
+                Err(_) => true,
+            };
+            if degenerate {
+                issues.push(ValidationIssue::new(
+                    IssueKind::DegenerateShape,
+                    json,
+                    Some(idx),
+                    format!("{:?} {:?} has no area", shape.shape_type, &shape.label),
+                ));
+            }
+        }
+        let outside = shape
+            .points
+            .iter()
+            .any(|p| p[0] < 0.0 || p[1] < 0.0 || p[0] > w || p[1] > h);
+        if outside {
+            issues.push(ValidationIssue::new(
+                IssueKind::OutOfBounds,
+                json,
+                Some(idx),
+                format!(
+                    "{:?} has points outside the {}x{} image",
+                    &shape.label, labelme.imageWidth, labelme.imageHeight
+                ),
+            ));
+        }
+    }
+    (issues, image)
+}
+
+fn validate_yolo_file(txt: &Path, options: &ValidateOptions) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = vec![];
+    let all_yolo = match read_yolo_from_txt(txt) {
+        Ok(all_yolo) => all_yolo,
+        Err(e) => {
+            issues.push(ValidationIssue::new(
+                IssueKind::MalformedLabel,
+                txt,
+                None,
+                format!("not a yolo txt: {}", e),
+            ));
+            return issues;
+        }
+    };
+    for (line, yolo) in all_yolo.iter().enumerate() {
+        let class_id = yolo.class();
+        if let Some(classes) = &options.classes {
+            if class_id < 0 || class_id as usize >= classes.len() {
+                issues.push(ValidationIssue::new(
+                    IssueKind::UnknownClass,
+                    txt,
+                    Some(line),
+                    format!("class id {} but only {} classes", class_id, classes.len()),
+                ));
+            }
+        }
+        if all_yolo[..line].contains(yolo) {
+            issues.push(ValidationIssue::new(
+                IssueKind::DuplicateShape,
+                txt,
+                Some(line),
+                String::from("same line twice in the file"),
+            ));
+        }
+        let (values, degenerate) = match yolo {
+            YoloLabel::Bbox(bbox) => (
+                // xmin/ymin hold the box center
+                vec![
+                    bbox.xmin - bbox.w / 2.0,
+                    bbox.ymin - bbox.h / 2.0,
+                    bbox.xmin + bbox.w / 2.0,
+                    bbox.ymin + bbox.h / 2.0,
+                ],
+                bbox.w <= 0.0 || bbox.h <= 0.0,
+            ),
+            YoloLabel::Segment(seg) => (
+                seg.points.iter().flatten().copied().collect(),
+                polygon_area(&seg.points) <= 0.0,
+            ),
+        };
+        if degenerate {
+            issues.push(ValidationIssue::new(
+                IssueKind::DegenerateShape,
+                txt,
+                Some(line),
+                String::from("has no area"),
+            ));
+        }
+        // a little slack for rounding in the writers
+        if values.iter().any(|v| *v < -1e-4 || *v > 1.0 + 1e-4) {
+            issues.push(ValidationIssue::new(
+                IssueKind::OutOfBounds,
+                txt,
+                Some(line),
+                String::from("coordinates outside 0..1"),
+            ));
+        }
+    }
+    issues
+}
+
+fn check_class(label: &str, options: &ValidateOptions) -> Option<String> {
+    match &options.classes {
+        Some(classes) if !classes.iter().any(|c| c == label) => {
+            Some(format!("{:?} is not in the class list", label))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_validate {
+    use crate::fileutils::write_labelme_to_json;
+    use crate::label::{read_labels_from_file, ShapeType};
+    use crate::validate::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn validate_finds_issues() {
+        let _tmp = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&_tmp).unwrap();
+        fs::copy("test/test.png", _tmp.join("test.png")).unwrap();
+
+        let mut _labelme = read_labels_from_file("test/test.json").unwrap();
+        let _valid = ValidateOptions {
+            check_image_size: true,
+            ..Default::default()
+        };
+        write_labelme_to_json(&_labelme, &_tmp.join("test.png")).unwrap();
+        let _report = validate_folder(&_tmp, &_valid).unwrap();
+        assert_eq!(_report.issues, vec![]);
+        assert!(!_report.failed(true));
+
+        fs::copy("test/test.png", _tmp.join("orphan.png")).unwrap();
+        fs::write(_tmp.join("broken.json"), "{ not json").unwrap();
+        fs::write(_tmp.join("lonely.txt"), "0 0.5 0.5 0.1 0.1").unwrap();
+        _labelme.imageWidth = 512;
+        _labelme.shapes.push(_labelme.shapes[0].to_owned());
+        _labelme.shapes[1].points = vec![vec![10.0, 10.0], vec![10.0, 50.0]];
+        _labelme.shapes[2].points = vec![vec![10.0, 10.0]];
+        _labelme.shapes[3].shape_type = ShapeType::Polygon;
+        _labelme.shapes[3].label = String::from("nose");
+        write_labelme_to_json(&_labelme, &_tmp.join("test.png")).unwrap();
+        let mut _other = read_labels_from_file("test/test.json").unwrap();
+        _other.imagePath = String::from("scan.gif");
+        fs::copy("test/test.png", _tmp.join("scan.gif")).unwrap();
+        write_labelme_to_json(&_other, &_tmp.join("scan.gif")).unwrap();
+        _other.imagePath = String::from("gone.png");
+        write_labelme_to_json(&_other, &_tmp.join("gone.png")).unwrap();
+
+        let _options = ValidateOptions {
+            classes: Some(vec![
+                String::from("ear"),
+                String::from("eye"),
+                String::from("mouth"),
+            ]),
+            check_image_size: true,
+            ..Default::default()
+        };
+        let _report = validate_folder(&_tmp, &_options).unwrap();
+        let _kinds: Vec<IssueKind> = _report.issues.iter().map(|i| i.kind).collect();
+        for _kind in [
+            IssueKind::MalformedLabel,
+            IssueKind::OrphanLabel,
+            IssueKind::OrphanImage,
+            IssueKind::DimensionMismatch,
+            IssueKind::DegenerateShape,
+            IssueKind::TooFewPoints,
+            IssueKind::UnknownClass,
+            IssueKind::DuplicateShape,
+            IssueKind::MissingImage,
+            IssueKind::UnsupportedImage,
+        ] {
+            assert!(_kinds.contains(&_kind), "{:?} not in {:?}", _kind, _kinds);
+        }
+        assert!(_report.failed(false));
+        let _json = serde_json::to_value(&_report).unwrap();
+        assert_eq!(_json["issues"][0]["severity"], "error");
+        assert!(_json["counts"]["malformed_label"].as_u64().unwrap() >= 1);
+        fs::remove_dir_all(&_tmp).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+/* pascal voc xml, one `.xml` per image */
+use crate::label::{skip_unsupported, LabelmeAnnotation, Shape, ShapeType};
+use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `Shape::flags` keys used for the voc object flags
+pub const DIFFICULT_FLAG: &str = "difficult";
+pub const TRUNCATED_FLAG: &str = "truncated";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "annotation")]
+pub struct VocAnnotation {
+    #[serde(default)]
+    pub folder: String,
+    pub filename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub size: VocSize,
+    #[serde(default)]
+    pub segmented: i64,
+    #[serde(default, rename = "object")]
+    pub objects: Vec<VocObject>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VocSize {
+    pub width: i64,
+    pub height: i64,
+    #[serde(default = "default_depth")]
+    pub depth: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VocObject {
+    pub name: String,
+    #[serde(default = "default_pose")]
+    pub pose: String,
+    #[serde(default)]
+    pub truncated: i64,
+    #[serde(default)]
+    pub difficult: i64,
+    pub bndbox: VocBndBox,
+}
+
+/// some tools write floats here, so we read floats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VocBndBox {
+    pub xmin: f32,
+    pub ymin: f32,
+    pub xmax: f32,
+    pub ymax: f32,
+}
+
+fn default_depth() -> i64 {
+    3
+}
+
+fn default_pose() -> String {
+    String::from("Unspecified")
+}
+
+impl VocAnnotation {
+    /// voc only knows boxes, so every shape with an area
+    /// is written as its tight bbox
+    pub fn from_labelme(labelme: &LabelmeAnnotation) -> Result<VocAnnotation, Error> {
+        let mut objects: Vec<VocObject> = vec![];
+        for shape in labelme.shapes.iter() {
+            if skip_unsupported(shape) || !shape.shape_type.has_area() {
+                continue;
+            }
+            let bbox = shape.bbox()?;
+            objects.push(VocObject {
+                name: shape.label.to_owned(),
+                pose: default_pose(),
+                truncated: shape.flag_is_set(TRUNCATED_FLAG) as i64,
+                difficult: shape.flag_is_set(DIFFICULT_FLAG) as i64,
+                bndbox: VocBndBox {
+                    xmin: bbox.x1,
+                    ymin: bbox.y1,
+                    xmax: bbox.x2,
+                    ymax: bbox.y2,
+                },
+            });
+        }
+        Ok(VocAnnotation {
+            folder: String::new(),
+            filename: labelme.imagePath.to_owned(),
+            path: None,
+            size: VocSize {
+                width: labelme.imageWidth,
+                height: labelme.imageHeight,
+                depth: default_depth(),
+            },
+            segmented: 0,
+            objects,
+        })
+    }
+
+    pub fn to_labelme(&self) -> LabelmeAnnotation {
+        let shapes: Vec<Shape> = self
+            .objects
+            .iter()
+            .map(|object| {
+                let mut shape = Shape {
+                    label: object.name.to_owned(),
+                    points: vec![
+                        vec![object.bndbox.xmin, object.bndbox.ymin],
+                        vec![object.bndbox.xmax, object.bndbox.ymax],
+                    ],
+                    group_id: None,
+                    shape_type: ShapeType::Rectangle,
+                    flags: Some(HashMap::new()),
+                    extra: serde_json::Map::new(),
+                };
+                if object.difficult != 0 {
+                    shape.set_flag(DIFFICULT_FLAG);
+                }
+                if object.truncated != 0 {
+                    shape.set_flag(TRUNCATED_FLAG);
+                }
+                shape
+            })
+            .collect();
+        LabelmeAnnotation::new(
+            Some(HashMap::new()),
+            shapes,
+            self.filename.to_owned(),
+            None,
+            self.size.width,
+            self.size.height,
+        )
+    }
+}
+
+pub fn read_voc_from_file(filename: &Path) -> Result<VocAnnotation, Error> {
+    let xml_str = fs::read_to_string(filename)?;
+    let voc: VocAnnotation = quick_xml::de::from_str(&xml_str)?;
+    Ok(voc)
+}
+
+/// writes `<image name>.xml` next to the given image path
+pub fn write_voc_to_xml(input_voc: &VocAnnotation, image_path: &Path) -> Result<(), Error> {
+    let mut xml_fname = image_path.to_owned();
+    xml_fname.set_extension("xml");
+    let mut xml_str = String::new();
+    let mut serializer = quick_xml::se::Serializer::new(&mut xml_str);
+    serializer.indent(' ', 2);
+    input_voc.serialize(serializer)?;
+    fs::write(xml_fname, xml_str)?;
+    Ok(())
+}
+
+/// get all classes from a list of voc xmls,
+/// sorted and deduped like `fileutils::get_all_classes`
+pub fn get_all_voc_classes(input: &[PathBuf]) -> Result<Vec<String>, Error> {
+    let mut label_list: Vec<String> = vec![];
+    for xml in input.iter() {
+        for object in read_voc_from_file(xml)?.objects.into_iter() {
+            label_list.push(object.name);
+        }
+    }
+    label_list.sort();
+    label_list.dedup();
+    Ok(label_list)
+}
+
+#[cfg(test)]
+mod test_voc {
+    use crate::label::read_labels_from_file;
+    use crate::voc::*;
+
+    #[test]
+    fn voc_round_trip() {
+        let mut _labelme = read_labels_from_file("test/test.json").unwrap();
+        _labelme.shapes[1].set_flag(DIFFICULT_FLAG);
+        let _voc = VocAnnotation::from_labelme(&_labelme).unwrap();
+        assert_eq!(_voc.objects.len(), 4);
+        assert_eq!(_voc.objects[1].difficult, 1);
+        assert_eq!(_voc.filename, "test.png");
+
+        let _xml = quick_xml::se::to_string(&_voc).unwrap();
+        assert!(_xml.starts_with("<annotation>"));
+        let _parsed: VocAnnotation = quick_xml::de::from_str(&_xml).unwrap();
+        assert_eq!(_parsed, _voc);
+
+        let _back = _parsed.to_labelme();
+        assert_eq!(_back.shapes.len(), 4);
+        assert_eq!(_back.shapes[0].points, _labelme.shapes[0].points);
+        assert!(_back.shapes[1].flag_is_set(DIFFICULT_FLAG));
+        assert!(!_back.shapes[0].flag_is_set(TRUNCATED_FLAG));
+    }
+
+    #[test]
+    fn voc_read_minimal() {
+        let _voc: VocAnnotation = quick_xml::de::from_str(
+            r#"<annotation>
+                <filename>a.jpg</filename>
+                <size><width>640</width><height>480</height></size>
+                <object>
+                    <name>card</name>
+                    <truncated>1</truncated>
+                    <bndbox><xmin>1</xmin><ymin>2</ymin><xmax>30.5</xmax><ymax>40</ymax></bndbox>
+                </object>
+            </annotation>"#,
+        )
+        .unwrap();
+        assert_eq!(_voc.size.depth, 3);
+        assert_eq!(_voc.objects[0].pose, "Unspecified");
+        let _labelme = _voc.to_labelme();
+        assert_eq!(_labelme.shapes[0].points[1], vec![30.5, 40.0]);
+        assert!(_labelme.shapes[0].flag_is_set(TRUNCATED_FLAG));
+    }
+}